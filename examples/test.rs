@@ -7,6 +7,8 @@ fn main() {
         r#"fn main {
     let {a: 25, b: 30, str: "Hello"};
     let {c: +{a, b}};
+    let {ready: true, missing: null};
+    let {big: (a + b) * 2 > 100 && ready};
 
     let {arr: [1, 2, 3, "hi", a, +{b, 10}, ${calc:(a: 5, b: 5)}]};
     let {list: [1, 2, 3]};
@@ -19,6 +21,8 @@ fn main() {
     ${calc:(a: a, b: b)};
 
     ${.{a, toString}:()};
+    // Calls with several arguments
+    ${.{console, log}: a, b, ${add: a, b}};
 
     when:{>{+{a, b}, 10}} {
         set {a: 20};
@@ -29,17 +33,33 @@ fn main() {
     }
 }
 
+/* Receives its arguments through the `param` object */
 fn calc {
     const {offset: 10};
 
     ret +{.{param, a}, -{.{param, b}, offset}};
 }
+
+fn add: {a b} {
+    ret +{a, b};
+}
+
+let {doubled: ${.{[1, 2, 3], map}: fn:{x} { ret *{x, 2}; }}};
 "#,
     );
 
-    let lexer = Lexer::new(source);
+    let lexer = Lexer::new(source.clone());
     let mut parser = Parser::new(lexer);
-    let transpiler = Transpiler::new(parser.parse(false));
 
-    println!("{}", transpiler.transpile());
+    match parser
+        .parse()
+        .and_then(|nodes| Transpiler::new(nodes).transpile())
+    {
+        Ok(code) => println!("{code}"),
+        Err(diagnostics) => {
+            for diagnostic in diagnostics {
+                eprintln!("{}", diagnostic.render(&source, true));
+            }
+        }
+    }
 }
@@ -1,15 +1,20 @@
-use std::collections::HashMap;
+use crate::lexer::Span;
 
 #[derive(Debug, PartialEq)]
 pub enum Value {
     Literal(String),
-    Number(i16),
+    Number(f64),
+    /// The digits of a BigInt literal, without the `n` suffix and digit separators.
+    BigInt(String),
+    Boolean(bool),
+    Null,
+    Undefined,
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Variable(pub String, pub Expression);
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Operator {
     Plus,
     Minus,
@@ -18,69 +23,205 @@ pub enum Operator {
     Gt,
     Lt,
     Equal,
+    NotEqual,
+    GtEqual,
+    LtEqual,
+    Modulo,
+    Power,
+    And,
+    Or,
 }
 
+impl Operator {
+    /// How tightly the operator binds in infix expressions, following JavaScript.
+    pub fn precedence(self) -> u8 {
+        match self {
+            Operator::Or => 1,
+            Operator::And => 2,
+            Operator::Equal | Operator::NotEqual => 3,
+            Operator::Gt | Operator::Lt | Operator::GtEqual | Operator::LtEqual => 4,
+            Operator::Plus | Operator::Minus => 5,
+            Operator::Times | Operator::Divided | Operator::Modulo => 6,
+            Operator::Power => 7,
+        }
+    }
+
+    pub fn is_right_associative(self) -> bool {
+        self == Operator::Power
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UnaryOperator {
+    Negate,
+    Not,
+}
+
+/// The key of a property access: `a.name` or `a[expression]`.
 #[derive(Debug, PartialEq)]
-pub enum Expression {
-    Value(Value),
+pub enum Property {
+    Named(String),
+    Computed(Box<Expression>),
+}
+
+/// The key of an object literal entry.
+#[derive(Debug, PartialEq)]
+pub enum ObjectKey {
+    /// `(name: value)`, also used by the `(name, other)` shorthand
     Identifier(String),
+    /// `("some key": value)`
+    String(String),
+    /// `([expression]: value)`
+    Computed(Box<Expression>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ObjectEntry {
+    pub key: ObjectKey,
+    pub value: Expression,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Expression {
+    Value(Value, Span),
+    Identifier(String, Span),
     Binary {
         left: Box<Expression>,
         op: Operator,
         right: Box<Expression>,
+        span: Span,
+    },
+    Unary {
+        op: UnaryOperator,
+        operand: Box<Expression>,
+        span: Span,
     },
     FunctionCall {
         function: Box<Expression>,
-        parameter: Box<Expression>,
+        arguments: Vec<Expression>,
+        span: Span,
     },
-    ArrayExpression(Vec<Box<Expression>>),
-    ObjectExpression(HashMap<String, Expression>),
+    ArrayExpression(Vec<Box<Expression>>, Span),
+    /// Entries are kept in source order, which JavaScript preserves for non-numeric keys.
+    ObjectExpression(Vec<ObjectEntry>, Span),
     PropertyAccess {
-        object: String,
-        property: Box<Expression>
+        object: Box<Expression>,
+        property: Property,
+        span: Span,
     },
     ArrayIndex {
         array: Box<Expression>,
-        index: Box<Expression>
+        index: Box<Expression>,
+        span: Span,
+    },
+    /// `fn:{a b} {...}` used as a value
+    Function {
+        parameters: Vec<String>,
+        content: Vec<ASTNode>,
+        span: Span,
+    },
+}
+
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Value(_, span)
+            | Expression::Identifier(_, span)
+            | Expression::Binary { span, .. }
+            | Expression::Unary { span, .. }
+            | Expression::FunctionCall { span, .. }
+            | Expression::ArrayExpression(_, span)
+            | Expression::ObjectExpression(_, span)
+            | Expression::PropertyAccess { span, .. }
+            | Expression::ArrayIndex { span, .. }
+            | Expression::Function { span, .. } => *span,
+        }
     }
 }
 
+// `For` holds three expressions, but there are few enough statements that boxing them would
+// not buy anything
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, PartialEq)]
 pub enum ASTNode {
     FunctionDeclaration {
-        start: usize,
+        span: Span,
         name: String,
+        /// `None` when the declaration has no parameter list, in which case the function
+        /// receives its arguments through a single `param` object.
+        parameters: Option<Vec<String>>,
         content: Vec<ASTNode>,
     },
     VariableDeclaration {
-        start: usize,
+        span: Span,
         vars: Vec<Variable>,
     },
     ConstDeclaration {
-        start: usize,
+        span: Span,
         vars: Vec<Variable>,
     },
     VariableSetting {
-        start: usize,
+        span: Span,
         vars: Vec<Variable>,
     },
     ReturnExpression {
-        start: usize,
+        span: Span,
         expression: Expression,
     },
     Expression(Expression),
-    WhenExpression {
-        start: usize,
-        expression: Expression,
+    /// A `when` followed by its `orwhen` and `or` blocks.
+    If {
+        span: Span,
+        branches: Vec<(Expression, Vec<ASTNode>)>,
+        else_branch: Option<Vec<ASTNode>>,
+    },
+    /// `loop:{condition} {...}`
+    While {
+        span: Span,
+        condition: Expression,
         content: Vec<ASTNode>,
     },
-    OrWhenExpression {
-        start: usize,
-        expression: Expression,
+    /// `loop:{variable, start, end, step} {...}`, counting from `start` up to `end` excluded,
+    /// or down to it when `step` is a negative number.
+    For {
+        span: Span,
+        variable: String,
+        start: Expression,
+        end: Expression,
+        step: Option<Expression>,
         content: Vec<ASTNode>,
     },
-    OrExpression {
-        start: usize,
+    /// `each:{item, iterable} {...}`
+    ForEach {
+        span: Span,
+        item: String,
+        iterable: Expression,
         content: Vec<ASTNode>,
     },
+    Break {
+        span: Span,
+    },
+    Continue {
+        span: Span,
+    },
+}
+
+impl ASTNode {
+    pub fn span(&self) -> Span {
+        match self {
+            ASTNode::FunctionDeclaration { span, .. }
+            | ASTNode::VariableDeclaration { span, .. }
+            | ASTNode::ConstDeclaration { span, .. }
+            | ASTNode::VariableSetting { span, .. }
+            | ASTNode::ReturnExpression { span, .. }
+            | ASTNode::If { span, .. }
+            | ASTNode::While { span, .. }
+            | ASTNode::For { span, .. }
+            | ASTNode::ForEach { span, .. }
+            | ASTNode::Break { span }
+            | ASTNode::Continue { span } => *span,
+            ASTNode::Expression(expression) => expression.span(),
+        }
+    }
 }
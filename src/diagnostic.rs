@@ -0,0 +1,162 @@
+use crate::lexer::LexError;
+use crate::lexer::Span;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    pub label: Option<String>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            span,
+            label: None,
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(message, span)
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Renders the diagnostic the way rustc does, quoting the offending line of `source` with
+    /// the span underlined. `colored` enables ANSI escape codes.
+    pub fn render(&self, source: &str, colored: bool) -> String {
+        let style = |code: &str, text: &str| {
+            if colored {
+                format!("\x1b[{code}m{text}\x1b[0m")
+            } else {
+                text.to_string()
+            }
+        };
+
+        let severity_code = match self.severity {
+            Severity::Error => "1;31",
+            Severity::Warning => "1;33",
+        };
+
+        let line_number = self.span.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        let pipe = style("1;34", "|");
+
+        let mut output = format!(
+            "{}{}\n{gutter}{} {}:{}\n",
+            style(severity_code, &self.severity.to_string()),
+            style("1", &format!(": {}", self.message)),
+            style("1;34", "-->"),
+            self.span.line,
+            self.span.column,
+        );
+
+        if let Some(line) = source.lines().nth(self.span.line.saturating_sub(1)) {
+            let before: String = line
+                .chars()
+                .take(self.span.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            // Only the first line of a multi-line span is underlined
+            let length = source
+                .get(self.span.start_byte..self.span.end_byte)
+                .and_then(|text| text.lines().next())
+                .map_or(0, |text| text.chars().count())
+                .max(1);
+
+            let mut underline = "^".repeat(length);
+
+            if let Some(label) = &self.label {
+                underline.push(' ');
+                underline.push_str(label);
+            }
+
+            output.push_str(&format!("{gutter} {pipe}\n"));
+            output.push_str(&format!("{} {pipe} {line}\n", style("1;34", &line_number)));
+            output.push_str(&format!(
+                "{gutter} {pipe} {before}{}\n",
+                style(severity_code, &underline)
+            ));
+        }
+
+        if !self.notes.is_empty() || self.help.is_some() {
+            output.push_str(&format!("{gutter} {pipe}\n"));
+        }
+
+        for note in &self.notes {
+            output.push_str(&format!("{gutter} {} {note}\n", style("1;34", "= note:")));
+        }
+
+        if let Some(help) = &self.help {
+            output.push_str(&format!("{gutter} {} {help}\n", style("1;34", "= help:")));
+        }
+
+        output
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} at line {}, column {}",
+            self.severity, self.message, self.span.line, self.span.column
+        )
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(error: LexError) -> Self {
+        let span = Span {
+            start_byte: error.offset,
+            end_byte: error.offset + error.text.len(),
+            line: error.line,
+            column: error.column,
+            utf16_column: error.utf16_column,
+            end_line: error.line,
+            end_column: error.column + error.text.chars().count(),
+        };
+
+        Diagnostic::error(error.kind.to_string(), span)
+    }
+}
@@ -1,3 +1,6 @@
+use std::error::Error;
+use std::fmt;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenKind {
     Fn,
@@ -8,6 +11,14 @@ pub enum TokenKind {
     When,
     OrWhen,
     Or,
+    Loop,
+    Each,
+    Break,
+    Continue,
+    True,
+    False,
+    Null,
+    Undefined,
     Obj,
     Colon,
     Comma,
@@ -21,6 +32,14 @@ pub enum TokenKind {
     Gt,
     Lt,
     Equal,
+    NotEqual,
+    GtEqual,
+    LtEqual,
+    Modulo,
+    Power,
+    LogicalAnd,
+    LogicalOr,
+    Not,
     LBrace,
     RBrace,
     LBracket,
@@ -30,23 +49,104 @@ pub enum TokenKind {
     Semicolon,
     Dot,
     At,
-    Dollar
+    Dollar,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Span {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub line: usize,
+    pub column: usize,
+    /// The column counted in UTF-16 code units, as source maps count them.
+    pub utf16_column: usize,
+    /// Line and column just past the end of the span, columns counting chars like `column`.
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    /// Returns a span covering both `self` and `other`, positioned at the start of `self`.
+    pub fn to(self, other: Span) -> Span {
+        let end = if other.end_byte > self.end_byte {
+            other
+        } else {
+            self
+        };
+
+        Span {
+            start_byte: self.start_byte,
+            end_byte: end.end_byte,
+            line: self.line,
+            column: self.column,
+            utf16_column: self.utf16_column,
+            end_line: end.end_line,
+            end_column: end.end_column,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token {
     pub kind: TokenKind,
     pub lexeme: String,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexErrorKind {
+    UnexpectedCharacter(char),
+    UnterminatedComment,
+    UnterminatedString,
+    InvalidEscape(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    pub kind: LexErrorKind,
     pub line: usize,
     pub column: usize,
+    pub utf16_column: usize,
+    pub offset: usize,
+    pub text: String,
 }
 
+impl fmt::Display for LexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character {c:?}"),
+            LexErrorKind::UnterminatedComment => write!(f, "unterminated block comment"),
+            LexErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+            LexErrorKind::InvalidEscape(escape) => {
+                write!(f, "invalid escape sequence `{escape}`")
+            }
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.line, self.column
+        )
+    }
+}
+
+impl Error for LexError {}
+
 pub struct Lexer {
     input: Vec<char>,
     position: usize,
+    offset: usize,
     line: usize,
     column: usize,
+    utf16_column: usize,
+    start_offset: usize,
+    start_line: usize,
     start_column: usize,
+    start_utf16_column: usize,
 }
 
 impl Lexer {
@@ -54,24 +154,32 @@ impl Lexer {
         Lexer {
             input: input.chars().collect(),
             position: 0,
+            offset: 0,
             line: 1,
             column: 1,
+            utf16_column: 1,
+            start_offset: 0,
+            start_line: 1,
             start_column: 1,
+            start_utf16_column: 1,
         }
     }
 
-    pub fn next_token(&mut self) -> Option<Token> {
-        self.skip_whitespace();
+    pub fn next_token(&mut self) -> Result<Option<Token>, LexError> {
+        self.skip_trivia()?;
 
         let current_char = self.current_char();
 
+        self.start_offset = self.offset;
+        self.start_line = self.line;
         self.start_column = self.column;
+        self.start_utf16_column = self.utf16_column;
 
         if self.position == self.input.len() {
-            return None;
+            return Ok(None);
         }
 
-        if self.lookup_ahead("fn") {
+        let token = if self.lookup_ahead("fn") {
             Some(self.new_token(TokenKind::Fn, "fn"))
         } else if self.lookup_ahead("ret") {
             Some(self.new_token(TokenKind::Return, "ret"))
@@ -87,6 +195,22 @@ impl Lexer {
             Some(self.new_token(TokenKind::OrWhen, "orwhen"))
         } else if self.lookup_ahead("or") {
             Some(self.new_token(TokenKind::Or, "or"))
+        } else if self.lookup_ahead("loop") {
+            Some(self.new_token(TokenKind::Loop, "loop"))
+        } else if self.lookup_ahead("each") {
+            Some(self.new_token(TokenKind::Each, "each"))
+        } else if self.lookup_ahead("break") {
+            Some(self.new_token(TokenKind::Break, "break"))
+        } else if self.lookup_ahead("continue") {
+            Some(self.new_token(TokenKind::Continue, "continue"))
+        } else if self.lookup_ahead("true") {
+            Some(self.new_token(TokenKind::True, "true"))
+        } else if self.lookup_ahead("false") {
+            Some(self.new_token(TokenKind::False, "false"))
+        } else if self.lookup_ahead("null") {
+            Some(self.new_token(TokenKind::Null, "null"))
+        } else if self.lookup_ahead("undefined") {
+            Some(self.new_token(TokenKind::Undefined, "undefined"))
         } else if let Some(char) = current_char {
             match char {
                 '{' => Some(self.new_token(TokenKind::LBrace, "{")),
@@ -96,70 +220,187 @@ impl Lexer {
                 ',' => Some(self.new_token(TokenKind::Comma, ",")),
                 '+' => Some(self.new_token(TokenKind::Plus, "+")),
                 '-' => Some(self.new_token(TokenKind::Minus, "-")),
+                '*' if self.peek(1) == Some('*') => Some(self.new_token(TokenKind::Power, "**")),
                 '*' => Some(self.new_token(TokenKind::Times, "*")),
                 '/' => Some(self.new_token(TokenKind::Divided, "/")),
+                '%' => Some(self.new_token(TokenKind::Modulo, "%")),
+                '>' if self.peek(1) == Some('=') => Some(self.new_token(TokenKind::GtEqual, ">=")),
                 '>' => Some(self.new_token(TokenKind::Gt, ">")),
+                '<' if self.peek(1) == Some('=') => Some(self.new_token(TokenKind::LtEqual, "<=")),
                 '<' => Some(self.new_token(TokenKind::Lt, "<")),
                 '=' => Some(self.new_token(TokenKind::Equal, "=")),
+                '!' if self.peek(1) == Some('=') => Some(self.new_token(TokenKind::NotEqual, "!=")),
+                '!' => Some(self.new_token(TokenKind::Not, "!")),
+                '&' if self.peek(1) == Some('&') => {
+                    Some(self.new_token(TokenKind::LogicalAnd, "&&"))
+                }
+                '|' if self.peek(1) == Some('|') => {
+                    Some(self.new_token(TokenKind::LogicalOr, "||"))
+                }
                 '[' => Some(self.new_token(TokenKind::LBracket, "[")),
                 ']' => Some(self.new_token(TokenKind::RBracket, "]")),
                 '(' => Some(self.new_token(TokenKind::LPar, "(")),
                 ')' => Some(self.new_token(TokenKind::RPar, ")")),
+                '.' if self.peek(1).is_some_and(|c| c.is_ascii_digit()) => {
+                    Some(self.number_literal())
+                }
                 '.' => Some(self.new_token(TokenKind::Dot, ".")),
                 '@' => Some(self.new_token(TokenKind::At, "@")),
                 '$' => Some(self.new_token(TokenKind::Dollar, "$")),
-                '"' => {
-                    let mut literal = String::from("");
+                '"' => Some(self.string_literal()?),
+                c if c.is_ascii_digit() => Some(self.number_literal()),
+                c if c.is_alphabetic() => {
+                    let mut identifier = String::from(c);
                     let mut length = 1;
 
                     while let Some(next_char) = self.peek(length) {
-                        if next_char == '"' {
+                        if !(next_char.is_alphanumeric() || next_char == '_') {
                             break;
                         }
 
-                        literal.push(next_char);
+                        identifier.push(next_char);
                         length += 1;
                     }
 
-                    self.advance(2);
-                    Some(self.new_token(TokenKind::StringLiteral, literal.as_str()))
+                    Some(self.new_token(TokenKind::Identifier, identifier.as_str()))
                 }
-                c if c.is_numeric() => {
-                    let mut number = String::from(c);
-                    let mut length = 1;
+                _ => {
+                    return Err(self.error(LexErrorKind::UnexpectedCharacter(char), 1));
+                }
+            }
+        } else {
+            None
+        };
 
-                    while let Some(next_char) = self.peek(length) {
-                        if !next_char.is_numeric() {
-                            break;
-                        }
+        Ok(token)
+    }
 
-                        number.push(next_char);
-                        length += 1;
-                    }
+    /// Lexes a number literal. Anything that could belong to it is included in the lexeme, the
+    /// parser is responsible for rejecting malformed numbers.
+    fn number_literal(&mut self) -> Token {
+        let mut number = String::new();
+        let prefixed = self.current_char() == Some('0')
+            && self
+                .peek(1)
+                .is_some_and(|c| matches!(c, 'x' | 'X' | 'o' | 'O' | 'b' | 'B'));
+
+        while let Some(c) = self.current_char() {
+            let fraction = !prefixed
+                && c == '.'
+                && !number.contains(['.', 'e', 'E'])
+                && self.peek(1).is_some_and(|c| c.is_ascii_digit());
+            let exponent_sign = !prefixed && matches!(c, '+' | '-') && number.ends_with(['e', 'E']);
+
+            if !(c.is_alphanumeric() || c == '_' || fraction || exponent_sign) {
+                break;
+            }
+
+            number.push(c);
+            self.advance(1);
+        }
+
+        self.token_from_start(TokenKind::Number, number)
+    }
+
+    /// Lexes a string literal, decoding its escape sequences into the token's lexeme.
+    fn string_literal(&mut self) -> Result<Token, LexError> {
+        let mut literal = String::new();
+        let mut error: Option<LexError> = None;
 
-                    Some(self.new_token(TokenKind::Number, number.as_str()))
+        self.advance(1);
+
+        loop {
+            match self.current_char() {
+                Some('"') => {
+                    self.advance(1);
+                    break;
                 }
-                c if c.is_alphabetic() => {
-                    let mut identifier = String::from(c);
-                    let mut length = 1;
+                Some('\\') => {
+                    let (offset, line, column, utf16_column) =
+                        (self.offset, self.line, self.column, self.utf16_column);
+                    let escape = self.escape_sequence();
 
-                    while let Some(next_char) = self.peek(length) {
-                        if !(next_char.is_alphanumeric() || next_char == '_') {
-                            break;
+                    match escape {
+                        Ok(c) => literal.push(c),
+                        // Keep going until the closing quote so that lexing resumes after the
+                        // string rather than in the middle of it
+                        Err(text) => {
+                            error.get_or_insert(LexError {
+                                kind: LexErrorKind::InvalidEscape(text.clone()),
+                                line,
+                                column,
+                                utf16_column,
+                                offset,
+                                text,
+                            });
                         }
-
-                        identifier.push(next_char);
-                        length += 1;
                     }
+                }
+                Some(c) => {
+                    literal.push(c);
+                    self.advance(1);
+                }
+                None => {
+                    return Err(LexError {
+                        kind: LexErrorKind::UnterminatedString,
+                        line: self.start_line,
+                        column: self.start_column,
+                        utf16_column: self.start_utf16_column,
+                        offset: self.start_offset,
+                        text: String::from("\""),
+                    });
+                }
+            }
+        }
 
-                    Some(self.new_token(TokenKind::Identifier, identifier.as_str()))
+        match error {
+            Some(error) => Err(error),
+            None => Ok(self.token_from_start(TokenKind::StringLiteral, literal)),
+        }
+    }
+
+    /// Consumes an escape sequence starting at the current `\\`, returning the character it
+    /// stands for or the text of the sequence when it is invalid.
+    fn escape_sequence(&mut self) -> Result<char, String> {
+        let escaped = self.peek(1);
+
+        self.advance(2);
+
+        match escaped {
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('0') => Ok('\0'),
+            Some('\\') => Ok('\\'),
+            Some('"') => Ok('"'),
+            Some('\'') => Ok('\''),
+            Some('u') => {
+                let mut text = String::from("\\u");
+
+                if self.current_char() != Some('{') {
+                    return Err(text);
                 }
-                _ => {
-                    panic!("Unexpected character: \"{char}\"");
+
+                while let Some(c) = self.current_char()
+                    && c != '"'
+                {
+                    text.push(c);
+                    self.advance(1);
+
+                    if c == '}' {
+                        break;
+                    }
                 }
+
+                text.strip_prefix("\\u{")
+                    .and_then(|hex| hex.strip_suffix('}'))
+                    .filter(|hex| !hex.is_empty() && hex.len() <= 6)
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32)
+                    .ok_or(text)
             }
-        } else {
-            None
+            Some(c) => Err(format!("\\{c}")),
+            None => Err(String::from("\\")),
         }
     }
 
@@ -174,11 +415,14 @@ impl Lexer {
             if c == '\n' {
                 self.line += 1;
                 self.column = 1;
+                self.utf16_column = 1;
             } else {
                 self.column += 1;
+                self.utf16_column += c.len_utf16();
             }
 
             self.position += 1;
+            self.offset += c.len_utf8();
         }
 
         self.current_char()
@@ -202,12 +446,67 @@ impl Lexer {
 
     fn lookup_ahead(&self, s: &str) -> bool {
         for (i, item) in s.chars().enumerate() {
-            if self.input[self.position + i] != item {
+            if self.peek(i) != Some(item) {
                 return false;
             }
         }
 
-        !self.input[self.position + s.len()].is_alphanumeric()
+        !self
+            .peek(s.len())
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+    }
+
+    /// Skips whitespace, `//` line comments and `/* */` block comments, which may be nested.
+    fn skip_trivia(&mut self) -> Result<(), LexError> {
+        loop {
+            self.skip_whitespace();
+
+            match (self.current_char(), self.peek(1)) {
+                (Some('/'), Some('/')) => {
+                    while let Some(c) = self.current_char()
+                        && c != '\n'
+                    {
+                        self.advance(1);
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    let (offset, line, column, utf16_column) =
+                        (self.offset, self.line, self.column, self.utf16_column);
+                    let mut depth = 0;
+
+                    loop {
+                        match (self.current_char(), self.peek(1)) {
+                            (Some('/'), Some('*')) => {
+                                depth += 1;
+                                self.advance(2);
+                            }
+                            (Some('*'), Some('/')) => {
+                                depth -= 1;
+                                self.advance(2);
+
+                                if depth == 0 {
+                                    break;
+                                }
+                            }
+                            (Some(_), _) => {
+                                self.advance(1);
+                            }
+                            (None, _) => {
+                                return Err(LexError {
+                                    kind: LexErrorKind::UnterminatedComment,
+                                    line,
+                                    column,
+                                    utf16_column,
+                                    offset,
+                                    text: String::from("/*"),
+                                });
+                            }
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
     }
 
     fn skip_whitespace(&mut self) {
@@ -220,14 +519,43 @@ impl Lexer {
         }
     }
 
+    fn error(&mut self, kind: LexErrorKind, length: usize) -> LexError {
+        let error = LexError {
+            kind,
+            line: self.start_line,
+            column: self.start_column,
+            utf16_column: self.start_utf16_column,
+            offset: self.start_offset,
+            text: self.input[self.position..self.position + length]
+                .iter()
+                .collect(),
+        };
+
+        // Skip the offending text so that the caller can resume lexing afterwards
+        self.advance(length);
+
+        error
+    }
+
     fn new_token(&mut self, kind: TokenKind, lexeme: &str) -> Token {
-        self.advance(lexeme.len());
+        self.advance(lexeme.chars().count());
+        self.token_from_start(kind, String::from(lexeme))
+    }
 
+    /// Builds a token spanning from the start of the current token to the current position.
+    fn token_from_start(&self, kind: TokenKind, lexeme: String) -> Token {
         Token {
             kind,
-            lexeme: String::from(lexeme),
-            line: self.line,
-            column: self.start_column,
+            lexeme,
+            span: Span {
+                start_byte: self.start_offset,
+                end_byte: self.offset,
+                line: self.start_line,
+                column: self.start_column,
+                utf16_column: self.start_utf16_column,
+                end_line: self.line,
+                end_column: self.column,
+            },
         }
     }
 }
@@ -1,16 +1,37 @@
 use crate::transpiler::Transpiler;
+use crate::transpiler::TranspileOptions;
+use crate::transpiler::TranspileOutput;
 use crate::parser::Parser;
+use crate::diagnostic::Diagnostic;
 use crate::lexer::Lexer;
 
 pub mod lexer;
 pub mod parser;
 pub mod ast;
+pub mod diagnostic;
 pub mod transpiler;
+pub mod sourcemap;
+mod mangle;
+
+pub fn transpile(input: String) -> Result<String, Vec<Diagnostic>> {
+    transpile_with(input, &TranspileOptions::default()).map(|output| output.code)
+}
+
+/// Transpiles `input` as configured by `options`. When a source map is asked for without
+/// `source_content`, the input is embedded in it.
+pub fn transpile_with(
+    input: String,
+    options: &TranspileOptions,
+) -> Result<TranspileOutput, Vec<Diagnostic>> {
+    let mut options = options.clone();
+
+    if let Some(source_map) = &mut options.source_map {
+        source_map.source_content.get_or_insert_with(|| input.clone());
+    }
 
-pub fn transpile(input: String) -> String {
     let lexer = Lexer::new(input);
     let mut parser = Parser::new(lexer);
-    let transpiler = Transpiler::new(parser.parse(false));
+    let transpiler = Transpiler::with_options(parser.parse()?, options);
 
-    transpiler.transpile()
+    transpiler.transpile_output()
 }
@@ -0,0 +1,282 @@
+use crate::ast::ASTNode;
+use crate::ast::Expression;
+use crate::ast::ObjectKey;
+use crate::ast::Property;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Characters a short name can start with, then the ones it can continue with.
+const FIRST_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_$";
+const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_$0123456789";
+
+/// Keywords and globals a short name could be. Longer ones are left out, as short names only
+/// reach five characters past 14 million names in a function.
+const RESERVED: &[&str] = &[
+    "do", "if", "in", "for", "let", "new", "try", "var", "NaN", "case", "else", "enum", "eval",
+    "null", "this", "true", "void", "with", "break", "catch", "class", "const", "false", "super",
+    "throw", "while", "yield", "async", "await",
+];
+
+/// Renames the variables and parameters declared inside functions to short names, following
+/// the scopes of the generated code. Names declared at top level are kept as they are.
+pub(crate) struct Mangler {
+    /// Every name of the program. Short names avoid them so that they never shadow a global.
+    taken: HashSet<String>,
+    scopes: Vec<Scope>,
+}
+
+struct Scope {
+    renames: HashMap<String, String>,
+    /// Whether the names declared in this scope are renamed, which is the case inside functions.
+    mangled: bool,
+    /// Index of the next short name. Sibling scopes start from the same index, so they reuse
+    /// the same short names.
+    next: usize,
+}
+
+impl Mangler {
+    pub fn new(nodes: &[ASTNode]) -> Self {
+        let mut taken = HashSet::new();
+
+        names_in_block(nodes, &mut taken);
+
+        Self {
+            taken,
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Enters the scope of a function whose parameters and body declare `names`.
+    pub fn enter_function<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) {
+        self.enter(names, true);
+    }
+
+    /// Enters a block declaring `names`, which are renamed if the block is inside a function.
+    pub fn enter_block<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) {
+        let mangled = self.scopes.last().is_some_and(|scope| scope.mangled);
+
+        self.enter(names, mangled);
+    }
+
+    pub fn exit(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Returns the name `name` is renamed to in the current scope.
+    pub fn name(&self, name: &str) -> String {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.renames.get(name))
+            .map_or_else(|| name.to_string(), String::clone)
+    }
+
+    fn enter<'a>(&mut self, names: impl IntoIterator<Item = &'a str>, mangled: bool) {
+        let mut scope = Scope {
+            renames: HashMap::new(),
+            mangled,
+            next: self.scopes.last().map_or(0, |scope| scope.next),
+        };
+
+        if mangled {
+            for name in names {
+                if !scope.renames.contains_key(name) {
+                    let short_name = self.short_name(&mut scope.next);
+
+                    scope.renames.insert(name.to_string(), short_name);
+                }
+            }
+        }
+
+        self.scopes.push(scope);
+    }
+
+    fn short_name(&self, next: &mut usize) -> String {
+        loop {
+            let name = short_name(*next);
+
+            *next += 1;
+
+            if !self.taken.contains(&name) && !RESERVED.contains(&name.as_str()) {
+                return name;
+            }
+        }
+    }
+}
+
+/// Returns the names a block declares itself, without those of the blocks nested in it.
+pub(crate) fn block_declarations(nodes: &[ASTNode]) -> Vec<&str> {
+    let mut names = Vec::new();
+
+    for node in nodes {
+        match node {
+            ASTNode::VariableDeclaration { vars, .. } | ASTNode::ConstDeclaration { vars, .. } => {
+                names.extend(vars.iter().map(|var| var.0.as_str()));
+            }
+            ASTNode::FunctionDeclaration { name, .. } => names.push(name),
+            _ => {}
+        }
+    }
+
+    names
+}
+
+/// Returns the `index`th short name: `a` to `$`, then `aa`, `ba`, and so on.
+fn short_name(mut index: usize) -> String {
+    let mut name = String::from(FIRST_CHARS[index % FIRST_CHARS.len()] as char);
+
+    index /= FIRST_CHARS.len();
+
+    while index > 0 {
+        index -= 1;
+        name.push(CHARS[index % CHARS.len()] as char);
+        index /= CHARS.len();
+    }
+
+    name
+}
+
+/// Collects every name declared or used in `nodes`.
+fn names_in_block(nodes: &[ASTNode], names: &mut HashSet<String>) {
+    for node in nodes {
+        match node {
+            ASTNode::FunctionDeclaration {
+                name,
+                parameters,
+                content,
+                ..
+            } => {
+                names.insert(name.clone());
+
+                match parameters {
+                    Some(parameters) => names.extend(parameters.iter().cloned()),
+                    None => {
+                        names.insert(String::from("param"));
+                    }
+                }
+
+                names_in_block(content, names);
+            }
+            ASTNode::VariableDeclaration { vars, .. }
+            | ASTNode::ConstDeclaration { vars, .. }
+            | ASTNode::VariableSetting { vars, .. } => {
+                for var in vars {
+                    names.insert(var.0.clone());
+                    names_in_expression(&var.1, names);
+                }
+            }
+            ASTNode::ReturnExpression { expression, .. } | ASTNode::Expression(expression) => {
+                names_in_expression(expression, names);
+            }
+            ASTNode::If {
+                branches,
+                else_branch,
+                ..
+            } => {
+                for (condition, content) in branches {
+                    names_in_expression(condition, names);
+                    names_in_block(content, names);
+                }
+
+                if let Some(content) = else_branch {
+                    names_in_block(content, names);
+                }
+            }
+            ASTNode::While {
+                condition, content, ..
+            } => {
+                names_in_expression(condition, names);
+                names_in_block(content, names);
+            }
+            ASTNode::For {
+                variable,
+                start,
+                end,
+                step,
+                content,
+                ..
+            } => {
+                names.insert(variable.clone());
+                names_in_expression(start, names);
+                names_in_expression(end, names);
+
+                if let Some(step) = step {
+                    names_in_expression(step, names);
+                }
+
+                names_in_block(content, names);
+            }
+            ASTNode::ForEach {
+                item,
+                iterable,
+                content,
+                ..
+            } => {
+                names.insert(item.clone());
+                names_in_expression(iterable, names);
+                names_in_block(content, names);
+            }
+            ASTNode::Break { .. } | ASTNode::Continue { .. } => {}
+        }
+    }
+}
+
+fn names_in_expression(expression: &Expression, names: &mut HashSet<String>) {
+    match expression {
+        Expression::Value(..) => {}
+        Expression::Identifier(name, _) => {
+            names.insert(name.clone());
+        }
+        Expression::Binary { left, right, .. } => {
+            names_in_expression(left, names);
+            names_in_expression(right, names);
+        }
+        Expression::Unary { operand, .. } => names_in_expression(operand, names),
+        Expression::FunctionCall {
+            function,
+            arguments,
+            ..
+        } => {
+            names_in_expression(function, names);
+
+            for argument in arguments {
+                names_in_expression(argument, names);
+            }
+        }
+        Expression::ArrayExpression(items, _) => {
+            for item in items {
+                names_in_expression(item, names);
+            }
+        }
+        Expression::ObjectExpression(entries, _) => {
+            for entry in entries {
+                if let ObjectKey::Computed(key) = &entry.key {
+                    names_in_expression(key, names);
+                }
+
+                names_in_expression(&entry.value, names);
+            }
+        }
+        Expression::PropertyAccess {
+            object, property, ..
+        } => {
+            names_in_expression(object, names);
+
+            if let Property::Computed(key) = property {
+                names_in_expression(key, names);
+            }
+        }
+        Expression::ArrayIndex { array, index, .. } => {
+            names_in_expression(array, names);
+            names_in_expression(index, names);
+        }
+        Expression::Function {
+            parameters,
+            content,
+            ..
+        } => {
+            names.extend(parameters.iter().cloned());
+            names_in_block(content, names);
+        }
+    }
+}
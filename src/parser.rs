@@ -1,451 +1,1188 @@
 use crate::ast::ASTNode;
 use crate::ast::Expression;
+use crate::ast::ObjectEntry;
+use crate::ast::ObjectKey;
 use crate::ast::Operator;
+use crate::ast::Property;
+use crate::ast::UnaryOperator;
 use crate::ast::Value;
 use crate::ast::Variable;
+use crate::diagnostic::Diagnostic;
 use crate::lexer::Lexer;
+use crate::lexer::Span;
 use crate::lexer::Token;
 use crate::lexer::TokenKind;
 use std::collections::HashMap;
 
 pub struct Parser {
     lexer: Lexer,
+    peeked: Option<Token>,
+    last_span: Span,
+    last_kind: Option<TokenKind>,
+    block_depth: usize,
+    loop_depth: usize,
+    open_braces: usize,
+    diagnostics: Vec<Diagnostic>,
 }
 
+// Diagnostics are only built once per syntax error, so boxing them would not buy anything
+#[allow(clippy::result_large_err)]
 impl Parser {
     pub fn new(lexer: Lexer) -> Self {
-        Self { lexer }
+        Self {
+            lexer,
+            peeked: None,
+            last_span: Span::default(),
+            last_kind: None,
+            block_depth: 0,
+            loop_depth: 0,
+            open_braces: 0,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Parses the whole input, recovering from syntax errors so that every problem in the
+    /// file is reported at once.
+    pub fn parse(&mut self) -> Result<Vec<ASTNode>, Vec<Diagnostic>> {
+        let nodes = self.parse_block(None);
+
+        if self.diagnostics.is_empty() {
+            Ok(nodes)
+        } else {
+            Err(std::mem::take(&mut self.diagnostics))
+        }
     }
 
-    pub fn parse(&mut self, stop_at_rbrace: bool) -> Vec<ASTNode> {
+    /// Parses statements until the end of the input or, when `opening_brace` is given, until
+    /// the `}` matching it.
+    fn parse_block(&mut self, opening_brace: Option<Span>) -> Vec<ASTNode> {
         let mut nodes = vec![];
 
-        while let Some(token) = self.lexer.next_token() {
-            if stop_at_rbrace && token.kind == TokenKind::RBrace {
-                return nodes;
+        self.block_depth += 1;
+
+        loop {
+            let token = match self.next_token() {
+                Ok(Some(token)) => token,
+                Ok(None) => break,
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    continue;
+                }
+            };
+
+            if token.kind == TokenKind::RBrace {
+                if opening_brace.is_some() {
+                    self.block_depth -= 1;
+                    return nodes;
+                }
+
+                self.diagnostics
+                    .push(Diagnostic::error("unexpected `}`", token.span));
+                continue;
             }
 
-            match token.kind {
-                TokenKind::Fn => {
-                    let start = token.column;
-                    let mut name = String::new();
-                    let mut parameters: Vec<String> = Vec::new();
-                    let mut content: Vec<ASTNode> = Vec::new();
-
-                    if let Some(token) = self.lexer.next_token() {
-                        if token.kind == TokenKind::Identifier {
-                            name = token.lexeme;
-                        } else {
-                            panic!(
-                                "Expected identifier after fn: {{line: {}, column: {}}}",
-                                token.line, token.column
-                            );
-                        }
-                    }
+            let open_braces = self.open_braces;
 
-                    if let Some(token) = self.lexer.next_token() {
-                        if token.kind == TokenKind::Colon
-                            && let Some(token) = self.lexer.next_token()
-                            && token.kind == TokenKind::LBrace
-                        {
-                            while let Some(token) = self.lexer.next_token() {
-                                if token.kind == TokenKind::RBrace {
-                                    break;
-                                }
-
-                                if token.kind == TokenKind::Identifier {
-                                    parameters.push(token.lexeme);
-                                }
-                            }
-
-                            if let Some(token) = self.lexer.next_token() {
-                                if token.kind == TokenKind::LBrace {
-                                    content = self.parse(true);
-                                }
-                            }
-                        } else if token.kind == TokenKind::LBrace {
-                            content = self.parse(true);
-                        }
-                    }
+            match self.parse_statement(token) {
+                Ok(Some(node)) => nodes.push(node),
+                Ok(None) => {}
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize(self.open_braces.saturating_sub(open_braces));
+                }
+            }
+        }
 
-                    nodes.push(ASTNode::FunctionDeclaration {
-                        start,
-                        name,
-                        content,
-                    })
+        if let Some(span) = opening_brace {
+            self.diagnostics.push(
+                Diagnostic::error("unclosed `{`", span)
+                    .with_label("this `{` is never closed")
+                    .with_note("expected `}` before the end of the file"),
+            );
+        }
+
+        self.block_depth -= 1;
+        nodes
+    }
+
+    fn parse_statement(&mut self, token: Token) -> Result<Option<ASTNode>, Diagnostic> {
+        let node = match token.kind {
+            TokenKind::Fn => {
+                let name = self.expect(TokenKind::Identifier, "identifier after `fn`")?;
+                let parameters = self.parse_parameters()?;
+                let brace = self.expect(TokenKind::LBrace, "`{` before the function body")?;
+                let content = self.parse_function_body(brace.span);
+
+                ASTNode::FunctionDeclaration {
+                    span: token.span.to(self.last_span),
+                    name: name.lexeme,
+                    parameters,
+                    content,
                 }
-                TokenKind::Let => {
-                    let start = token.column;
+            }
+            TokenKind::Let => {
+                let vars = self.parse_vars(&token)?;
 
-                    nodes.push(ASTNode::VariableDeclaration {
-                        start,
-                        vars: self.parse_vars(token),
-                    })
+                self.expect(TokenKind::Semicolon, "`;` after the variable list")?;
+
+                ASTNode::VariableDeclaration {
+                    span: token.span.to(self.last_span),
+                    vars,
                 }
-                TokenKind::Set => {
-                    let start = token.column;
+            }
+            TokenKind::Set => {
+                let vars = self.parse_vars(&token)?;
 
-                    nodes.push(ASTNode::VariableSetting {
-                        start,
-                        vars: self.parse_vars(token),
-                    })
+                self.expect(TokenKind::Semicolon, "`;` after the variable list")?;
+
+                ASTNode::VariableSetting {
+                    span: token.span.to(self.last_span),
+                    vars,
                 }
-                TokenKind::Const => {
-                    let start = token.column;
+            }
+            TokenKind::Const => {
+                let vars = self.parse_vars(&token)?;
 
-                    nodes.push(ASTNode::ConstDeclaration {
-                        start,
-                        vars: self.parse_vars(token),
-                    })
+                self.expect(TokenKind::Semicolon, "`;` after the variable list")?;
+
+                ASTNode::ConstDeclaration {
+                    span: token.span.to(self.last_span),
+                    vars,
                 }
-                TokenKind::Dollar => {
-                    let mut name: Option<Expression> = None;
-                    let mut parameter: Option<Expression> = None;
+            }
+            TokenKind::Dollar => {
+                let call = self.parse_call(token.span)?;
 
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::LBrace
-                    {
-                        name = Some(self.parse_expression());
+                self.expect(TokenKind::Semicolon, "`;` after the function call")?;
 
-                        if let Some(token) = self.lexer.next_token() {
-                            if token.kind == TokenKind::Colon {
-                                parameter = Some(self.parse_expression());
-                            }
-                        }
-                    }
+                ASTNode::Expression(call)
+            }
+            TokenKind::Return => {
+                let expression = self.parse_expression()?;
 
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::RBrace
-                    {}
+                self.expect(TokenKind::Semicolon, "`;` after the returned expression")?;
 
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::Semicolon
-                    {}
+                ASTNode::ReturnExpression {
+                    span: token.span.to(self.last_span),
+                    expression,
+                }
+            }
+            TokenKind::When => {
+                let mut branches = vec![self.parse_condition_block(&token)?];
+                let mut else_branch: Option<Vec<ASTNode>> = None;
 
-                    if let Some(name) = name
-                        && let Some(parameter) = parameter
-                    {
-                        nodes.push(ASTNode::Expression(Expression::FunctionCall {
-                            function: Box::new(name),
-                            parameter: Box::new(parameter),
-                        }))
-                    }
+                while let Some(token) = self.peek_token()?.cloned()
+                    && token.kind == TokenKind::OrWhen
+                {
+                    self.next_token()?;
+                    branches.push(self.parse_condition_block(&token)?);
                 }
-                TokenKind::Return => {
-                    let start = token.column;
-                    let expression = self.parse_expression();
 
-                    nodes.push(ASTNode::ReturnExpression { start, expression })
+                if self.eat(TokenKind::Or)? {
+                    let brace = self.expect(TokenKind::LBrace, "`{` after `or`")?;
+
+                    else_branch = Some(self.parse_block(Some(brace.span)));
                 }
-                TokenKind::When => {
-                    let start = token.column;
-                    let mut expression = Expression::Identifier(String::new());
-                    let mut content: Vec<ASTNode> = Vec::new();
 
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::Colon
-                        && let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::LBrace
-                    {
-                        expression = self.parse_expression();
+                ASTNode::If {
+                    span: token.span.to(self.last_span),
+                    branches,
+                    else_branch,
+                }
+            }
+            TokenKind::Loop => {
+                self.expect(TokenKind::Colon, "`:` after `loop`")?;
+                self.expect(TokenKind::LBrace, "`{` before the loop condition")?;
+
+                let condition = self.parse_expression()?;
 
-                        while let Some(token) = self.lexer.next_token() {
-                            if token.kind == TokenKind::RBrace {
-                                continue;
-                            }
+                if self.eat(TokenKind::Comma)? {
+                    let Expression::Identifier(variable, _) = condition else {
+                        return Err(Diagnostic::error(
+                            "expected the name of the loop variable",
+                            condition.span(),
+                        )
+                        .with_help("counted loops are written `loop:{i, start, end} {...}`"));
+                    };
 
-                            if token.kind == TokenKind::LBrace {
-                                content = self.parse(true);
+                    let start = self.parse_expression()?;
 
-                                break;
-                            }
-                        }
+                    self.expect(TokenKind::Comma, "`,` after the start of the range")?;
+
+                    let end = self.parse_expression()?;
+                    let step = if self.eat(TokenKind::Comma)? {
+                        Some(self.parse_expression()?)
+                    } else {
+                        None
+                    };
+
+                    // Reported without failing the loop, the body is still worth checking
+                    if let Some(step) = &step
+                        && is_zero(step)
+                    {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                "the step of a counted loop cannot be zero",
+                                step.span(),
+                            )
+                            .with_note("the loop would never end"),
+                        );
                     }
 
-                    nodes.push(ASTNode::WhenExpression {
+                    self.expect(TokenKind::RBrace, "`}` after the range")?;
+
+                    let brace = self.expect(TokenKind::LBrace, "`{` before the loop body")?;
+                    let content = self.parse_loop_body(brace.span);
+
+                    ASTNode::For {
+                        span: token.span.to(self.last_span),
+                        variable,
                         start,
-                        expression,
+                        end,
+                        step,
                         content,
-                    })
+                    }
+                } else {
+                    self.expect(TokenKind::RBrace, "`}` after the loop condition")?;
+
+                    let brace = self.expect(TokenKind::LBrace, "`{` before the loop body")?;
+                    let content = self.parse_loop_body(brace.span);
+
+                    ASTNode::While {
+                        span: token.span.to(self.last_span),
+                        condition,
+                        content,
+                    }
                 }
-                TokenKind::OrWhen => {
-                    let start = token.column;
-                    let mut expression = Expression::Identifier(String::new());
-                    let mut content: Vec<ASTNode> = Vec::new();
+            }
+            TokenKind::Each => {
+                self.expect(TokenKind::Colon, "`:` after `each`")?;
+                self.expect(TokenKind::LBrace, "`{` before the loop item")?;
 
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::Colon
-                        && let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::LBrace
-                    {
-                        expression = self.parse_expression();
+                let item = self.expect(TokenKind::Identifier, "name of the loop item")?;
 
-                        while let Some(token) = self.lexer.next_token() {
-                            if token.kind == TokenKind::RBrace {
-                                continue;
-                            }
+                self.expect(TokenKind::Comma, "`,` after the loop item")?;
 
-                            if token.kind == TokenKind::LBrace {
-                                content = self.parse(true);
+                let iterable = self.parse_expression()?;
 
-                                break;
-                            }
-                        }
-                    }
+                self.expect(TokenKind::RBrace, "`}` after the iterated expression")?;
 
-                    nodes.push(ASTNode::OrWhenExpression {
-                        start,
-                        expression,
-                        content,
-                    })
+                let brace = self.expect(TokenKind::LBrace, "`{` before the loop body")?;
+                let content = self.parse_loop_body(brace.span);
+
+                ASTNode::ForEach {
+                    span: token.span.to(self.last_span),
+                    item: item.lexeme,
+                    iterable,
+                    content,
+                }
+            }
+            TokenKind::Break | TokenKind::Continue => {
+                if self.loop_depth == 0 {
+                    return Err(Diagnostic::error(
+                        format!("`{}` outside of a loop", token.lexeme),
+                        token.span,
+                    ));
                 }
-                TokenKind::Or => {
-                    let start = token.column;
-                    let mut content: Vec<ASTNode> = Vec::new();
 
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::LBrace
-                    {
-                        content = self.parse(true);
-                    }
+                self.expect(
+                    TokenKind::Semicolon,
+                    &format!("`;` after `{}`", token.lexeme),
+                )?;
 
-                    nodes.push(ASTNode::OrExpression { start, content })
+                let span = token.span.to(self.last_span);
+
+                match token.kind {
+                    TokenKind::Break => ASTNode::Break { span },
+                    _ => ASTNode::Continue { span },
                 }
-                _ => {}
             }
+            TokenKind::OrWhen | TokenKind::Or => {
+                return Err(Diagnostic::error(
+                    format!("`{}` without a preceding `when`", token.lexeme),
+                    token.span,
+                )
+                .with_help("start the chain with `when:{...} {...}`"));
+            }
+            // Stray semicolons are empty statements
+            TokenKind::Semicolon => return Ok(None),
+            _ => {
+                return Err(Diagnostic::error(
+                    format!("expected statement, found `{}`", token.lexeme),
+                    token.span,
+                ));
+            }
+        };
+
+        Ok(Some(node))
+    }
+
+    /// Parses the optional `:{a b}` parameter list of a function.
+    fn parse_parameters(&mut self) -> Result<Option<Vec<String>>, Diagnostic> {
+        if !self.eat(TokenKind::Colon)? {
+            return Ok(None);
+        }
+
+        let mut parameters = Vec::new();
+
+        self.expect(TokenKind::LBrace, "`{` before the parameter list")?;
+
+        while !self.eat(TokenKind::RBrace)? {
+            if self.eat(TokenKind::Comma)? {
+                continue;
+            }
+
+            parameters.push(self.expect(TokenKind::Identifier, "parameter name")?.lexeme);
         }
 
-        nodes
+        Ok(Some(parameters))
     }
 
-    fn parse_expression(&mut self) -> Expression {
-        let mut expression = Expression::Value(Value::Literal(String::new()));
+    /// Parses the `.name.other` chain following the object of a property access.
+    fn parse_dotted_names(&mut self, mut object: Expression) -> Result<Expression, Diagnostic> {
+        while self.eat(TokenKind::Dot)? {
+            let name = self.expect(TokenKind::Identifier, "property name after `.`")?;
 
-        if let Some(token) = self.lexer.next_token() {
-            match token.kind {
-                TokenKind::StringLiteral => {
-                    expression = Expression::Value(Value::Literal(token.lexeme));
-                }
-                TokenKind::Number => {
-                    expression =
-                        Expression::Value(Value::Number(token.lexeme.parse::<i16>().unwrap()));
-                }
-                TokenKind::Identifier => expression = Expression::Identifier(token.lexeme),
-                TokenKind::Plus
-                | TokenKind::Minus
-                | TokenKind::Times
-                | TokenKind::Divided
-                | TokenKind::Gt
-                | TokenKind::Lt
-                | TokenKind::Equal => {
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::LBrace
-                    {}
-
-                    let left = self.parse_expression();
-
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::Comma
-                    {}
-
-                    let right = self.parse_expression();
-
-                    expression = Expression::Binary {
-                        left: Box::new(left),
-                        op: match token.kind {
-                            TokenKind::Plus => Operator::Plus,
-                            TokenKind::Minus => Operator::Minus,
-                            TokenKind::Times => Operator::Times,
-                            TokenKind::Divided => Operator::Divided,
-                            TokenKind::Gt => Operator::Gt,
-                            TokenKind::Lt => Operator::Lt,
-                            TokenKind::Equal => Operator::Equal,
-                            // Impossible
-                            _ => Operator::Plus,
-                        },
-                        right: Box::new(right),
-                    }
+            object = property_access(object, Property::Named(name.lexeme), name.span);
+        }
+
+        Ok(object)
+    }
+
+    /// Parses a property after a `,` in `.{object, property}`: a name with an optional dotted
+    /// chain, a `[key]` expression, a string or number key, or a nested `.{inner, text}`.
+    fn parse_property(&mut self, object: Expression) -> Result<Expression, Diagnostic> {
+        let kind = self.peek_token()?.map(|token| token.kind.clone());
+
+        match kind {
+            Some(TokenKind::Identifier) => {
+                let name = self.expect(TokenKind::Identifier, "property name")?;
+                let access = property_access(object, Property::Named(name.lexeme), name.span);
+
+                self.parse_dotted_names(access)
+            }
+            Some(TokenKind::LBracket) => {
+                self.expect(TokenKind::LBracket, "`[`")?;
+
+                let key = self.parse_expression()?;
+
+                self.expect(TokenKind::RBracket, "`]` after the computed property")?;
+
+                Ok(property_access(
+                    object,
+                    Property::Computed(Box::new(key)),
+                    self.last_span,
+                ))
+            }
+            Some(TokenKind::StringLiteral | TokenKind::Number) => {
+                let key = self.parse_operand()?;
+                let end = key.span();
+
+                Ok(property_access(
+                    object,
+                    Property::Computed(Box::new(key)),
+                    end,
+                ))
+            }
+            Some(TokenKind::Dot) => {
+                let nested = self.parse_operand()?;
+
+                Self::chain_onto(object, nested)
+            }
+            _ => Err(self
+                .unexpected("property name")
+                .with_help("computed properties are written `.{object, [key]}`")),
+        }
+    }
+
+    /// Rewrites the legacy nested form `.{object, .{inner, text}}` into `object.inner.text` by
+    /// accessing the innermost name of `access` on `object`.
+    fn chain_onto(object: Expression, access: Expression) -> Result<Expression, Diagnostic> {
+        match access {
+            Expression::PropertyAccess {
+                object: inner,
+                property,
+                span,
+            } => Ok(property_access(
+                Self::chain_onto(object, *inner)?,
+                property,
+                span,
+            )),
+            Expression::Identifier(name, span) => {
+                Ok(property_access(object, Property::Named(name), span))
+            }
+            access => Err(Diagnostic::error("expected property name", access.span())
+                .with_help("computed properties are written `.{object, [key]}`")),
+        }
+    }
+
+    /// Parses the body of a function, in which `break` and `continue` cannot reach the loops
+    /// around the function.
+    fn parse_function_body(&mut self, opening_brace: Span) -> Vec<ASTNode> {
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let content = self.parse_block(Some(opening_brace));
+
+        self.loop_depth = loop_depth;
+        content
+    }
+
+    fn parse_loop_body(&mut self, opening_brace: Span) -> Vec<ASTNode> {
+        self.loop_depth += 1;
+
+        let content = self.parse_block(Some(opening_brace));
+
+        self.loop_depth -= 1;
+        content
+    }
+
+    /// Parses the `:{condition} {...}` part shared by `when` and `orwhen`.
+    fn parse_condition_block(
+        &mut self,
+        token: &Token,
+    ) -> Result<(Expression, Vec<ASTNode>), Diagnostic> {
+        self.expect(TokenKind::Colon, &format!("`:` after `{}`", token.lexeme))?;
+        self.expect(TokenKind::LBrace, "`{` before the condition")?;
+
+        let expression = self.parse_expression()?;
+
+        self.expect(TokenKind::RBrace, "`}` after the condition")?;
+
+        let brace = self.expect(TokenKind::LBrace, "`{` before the block")?;
+        let content = self.parse_block(Some(brace.span));
+
+        Ok((expression, content))
+    }
+
+    /// Parses an expression written either in prefix form (`>{+{a, *{b, c}}, 10}`) or with
+    /// infix operators (`a + b * c > 10`), both forms producing the same tree.
+    fn parse_expression(&mut self) -> Result<Expression, Diagnostic> {
+        self.parse_binary(0)
+    }
+
+    /// Parses an operand followed by any infix operators binding at least as tightly as
+    /// `min_precedence`.
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expression, Diagnostic> {
+        let left = self.parse_operand()?;
+
+        self.parse_binary_rest(left, min_precedence)
+    }
+
+    /// Continues a precedence climbing parse from an already parsed left operand.
+    fn parse_binary_rest(
+        &mut self,
+        mut left: Expression,
+        min_precedence: u8,
+    ) -> Result<Expression, Diagnostic> {
+        while let Some(op) = self
+            .peek_token()?
+            .and_then(|token| binary_operator(&token.kind))
+            && op.precedence() >= min_precedence
+        {
+            self.next_token()?;
+
+            let right = if op.is_right_associative() {
+                self.parse_binary(op.precedence())?
+            } else {
+                self.parse_binary(op.precedence() + 1)?
+            };
+
+            left = Expression::Binary {
+                span: left.span().to(right.span()),
+                left: Box::new(left),
+                op,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parses a single operand: a value, a prefix form such as `+{a, b}`, or an infix unary
+    /// operator applied to an operand.
+    fn parse_operand(&mut self) -> Result<Expression, Diagnostic> {
+        let Some(token) = self.next_token()? else {
+            return Err(self.unexpected("expression"));
+        };
+
+        let expression = match token.kind {
+            TokenKind::StringLiteral => Expression::Value(Value::Literal(token.lexeme), token.span),
+            TokenKind::Number => Expression::Value(self.parse_number(&token)?, token.span),
+            TokenKind::Identifier => Expression::Identifier(token.lexeme, token.span),
+            TokenKind::True => Expression::Value(Value::Boolean(true), token.span),
+            TokenKind::False => Expression::Value(Value::Boolean(false), token.span),
+            TokenKind::Null => Expression::Value(Value::Null, token.span),
+            TokenKind::Undefined => Expression::Value(Value::Undefined, token.span),
+            // Without braces, `-a` and `!a` bind tighter than every binary operator but `**`
+            TokenKind::Minus | TokenKind::Not if !self.check(TokenKind::LBrace)? => {
+                let operand = self.parse_binary(Operator::Power.precedence())?;
+
+                Expression::Unary {
+                    op: match token.kind {
+                        TokenKind::Not => UnaryOperator::Not,
+                        _ => UnaryOperator::Negate,
+                    },
+                    span: token.span.to(operand.span()),
+                    operand: Box::new(operand),
                 }
-                TokenKind::Dollar => {
-                    let mut name: Option<Expression> = None;
-                    let mut parameter: Option<Expression> = None;
+            }
+            TokenKind::Fn => {
+                let parameters = self.parse_parameters()?.unwrap_or_default();
+                let brace = self.expect(TokenKind::LBrace, "`{` before the function body")?;
+                let content = self.parse_function_body(brace.span);
 
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::LBrace
-                    {
-                        name = Some(self.parse_expression());
+                Expression::Function {
+                    parameters,
+                    content,
+                    span: token.span.to(self.last_span),
+                }
+            }
+            TokenKind::Not => {
+                self.expect(TokenKind::LBrace, "`{` after `!`")?;
 
-                        if let Some(token) = self.lexer.next_token() {
-                            if token.kind == TokenKind::Colon {
-                                parameter = Some(self.parse_expression());
-                            }
-                        }
-                    }
+                let operand = self.parse_expression()?;
 
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::RBrace
-                    {}
+                self.expect(TokenKind::RBrace, "`}` after the operand")?;
 
-                    if let Some(name) = name
-                        && let Some(parameter) = parameter
-                    {
-                        expression = Expression::FunctionCall {
-                            function: Box::new(name),
-                            parameter: Box::new(parameter),
-                        }
-                    }
+                Expression::Unary {
+                    op: UnaryOperator::Not,
+                    operand: Box::new(operand),
+                    span: token.span.to(self.last_span),
                 }
-                TokenKind::LBracket => {
-                    let mut items: Vec<Box<Expression>> = vec![Box::new(self.parse_expression())];
+            }
+            TokenKind::Plus
+            | TokenKind::Minus
+            | TokenKind::Times
+            | TokenKind::Divided
+            | TokenKind::Modulo
+            | TokenKind::Power
+            | TokenKind::Gt
+            | TokenKind::Lt
+            | TokenKind::GtEqual
+            | TokenKind::LtEqual
+            | TokenKind::Equal
+            | TokenKind::NotEqual
+            | TokenKind::LogicalAnd
+            | TokenKind::LogicalOr => {
+                let mut operands: Vec<Expression> = Vec::new();
+
+                self.expect(TokenKind::LBrace, &format!("`{{` after `{}`", token.lexeme))?;
 
-                    while let Some(token) = self.lexer.next_token() {
-                        if token.kind == TokenKind::Comma {
-                            items.push(Box::new(self.parse_expression()));
-                        } else if token.kind == TokenKind::RBracket {
-                            break;
-                        }
+                while !self.eat(TokenKind::RBrace)? {
+                    operands.push(self.parse_expression()?);
+
+                    if !self.eat(TokenKind::Comma)? {
+                        self.expect(TokenKind::RBrace, "`,` or `}` after the operand")?;
+                        break;
                     }
+                }
+
+                let span = token.span.to(self.last_span);
 
-                    expression = Expression::ArrayExpression(items);
+                // `-{a}` negates its single operand
+                if token.kind == TokenKind::Minus && operands.len() == 1 {
+                    return Ok(Expression::Unary {
+                        op: UnaryOperator::Negate,
+                        operand: Box::new(operands.remove(0)),
+                        span,
+                    });
                 }
-                TokenKind::LPar => {
-                    let mut hash_map = HashMap::new();
 
-                    let mut current_name: Option<String> = None;
+                if operands.len() < 2 {
+                    return Err(Diagnostic::error(
+                        format!(
+                            "`{}` takes at least two operands, found {}",
+                            token.lexeme,
+                            operands.len()
+                        ),
+                        span,
+                    ));
+                }
+
+                // Impossible to fall back, every kind matched above is an operator
+                let op = binary_operator(&token.kind).unwrap_or(Operator::Plus);
+
+                // `+{a, b, c}` is `+{+{a, b}, c}`
+                let first = operands.remove(0);
+
+                operands
+                    .into_iter()
+                    .fold(first, |left, right| Expression::Binary {
+                        span: token.span.to(right.span()),
+                        left: Box::new(left),
+                        op,
+                        right: Box::new(right),
+                    })
+            }
+            TokenKind::Dollar => self.parse_call(token.span)?,
+            TokenKind::LBracket => {
+                let mut items: Vec<Box<Expression>> = Vec::new();
 
-                    while let Some(token) = self.lexer.next_token() {
-                        if token.kind == TokenKind::Identifier {
-                            current_name = Some(token.lexeme);
-                        } else if token.kind == TokenKind::Colon {
-                            let expr = self.parse_expression();
+                while !self.eat(TokenKind::RBracket)? {
+                    items.push(Box::new(self.parse_expression()?));
 
-                            if let Some(name) = current_name.take() {
-                                hash_map.insert(name, expr);
-                            }
-                        } else if token.kind == TokenKind::RPar {
-                            break;
-                        }
+                    if !self.eat(TokenKind::Comma)? {
+                        self.expect(TokenKind::RBracket, "`,` or `]`")?;
+                        break;
                     }
+                }
 
-                    expression = Expression::ObjectExpression(hash_map);
+                Expression::ArrayExpression(items, token.span.to(self.last_span))
+            }
+            TokenKind::LPar => {
+                if self.check(TokenKind::RPar)? {
+                    return self.parse_object(token.span, None);
                 }
-                TokenKind::Dot => {
-                    let mut object: Option<String> = None;
-                    let mut property: Option<Expression> = None;
 
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::LBrace
-                    {
-                        if let Some(identifier) = self.lexer.next_token() {
-                            object = Some(identifier.lexeme);
-                        }
-
-                        if let Some(token) = self.lexer.next_token()
-                            && token.kind == TokenKind::Comma
-                        {
-                            property = Some(self.parse_expression());
-
-                            if let Some(token) = self.lexer.next_token()
-                                && token.kind == TokenKind::RBrace
-                            {}
-                        }
-                    }
+                // `(key: ...)` and `(name, ...)` are objects, anything else is a parenthesized
+                // expression
+                let first = self.parse_operand()?;
 
-                    if let Some(object) = object
-                        && let Some(property) = property
-                    {
-                        expression = Expression::PropertyAccess {
-                            object,
-                            property: Box::new(property),
-                        }
-                    }
+                if self.check(TokenKind::Colon)? || self.check(TokenKind::Comma)? {
+                    self.parse_object(token.span, Some(first))?
+                } else {
+                    let expression = self.parse_binary_rest(first, 0)?;
+
+                    self.expect(TokenKind::RPar, "`)`")?;
+
+                    expression
                 }
-                TokenKind::At => {
-                    let mut array: Option<Expression> = None;
-                    let mut index: Option<Expression> = None;
+            }
+            TokenKind::Dot => {
+                self.expect(TokenKind::LBrace, "`{` after `.`")?;
 
-                    if let Some(token) = self.lexer.next_token()
-                        && token.kind == TokenKind::LBrace
-                    {
-                        array = Some(self.parse_expression());
+                let object = self.parse_expression()?;
+                let dotted = self.check(TokenKind::Dot)?;
+                let mut access = self.parse_dotted_names(object)?;
 
-                        if let Some(token) = self.lexer.next_token()
-                            && token.kind == TokenKind::Comma
-                        {
-                            index = Some(self.parse_expression());
-                        }
-                    }
+                if !dotted && !self.check(TokenKind::Comma)? {
+                    return Err(self.unexpected("`,` after the object"));
+                }
 
-                    if let Some(array) = array
-                        && let Some(index) = index
-                    {
-                        expression = Expression::ArrayIndex {
-                            array: Box::new(array),
-                            index: Box::new(index),
-                        }
-                    }
+                while self.eat(TokenKind::Comma)? {
+                    access = self.parse_property(access)?;
                 }
-                TokenKind::Comma => {
-                    return self.parse_expression();
+
+                self.expect(TokenKind::RBrace, "`}` after the property")?;
+
+                // The outermost access covers the whole `.{...}`
+                match access {
+                    Expression::PropertyAccess {
+                        object, property, ..
+                    } => Expression::PropertyAccess {
+                        object,
+                        property,
+                        span: token.span.to(self.last_span),
+                    },
+                    access => access,
                 }
-                TokenKind::Semicolon => {
-                    panic!("Expected expression at column: {}", token.column);
+            }
+            TokenKind::At => {
+                self.expect(TokenKind::LBrace, "`{` after `@`")?;
+
+                let array = self.parse_expression()?;
+
+                self.expect(TokenKind::Comma, "`,` after the array")?;
+
+                let index = self.parse_expression()?;
+
+                self.expect(TokenKind::RBrace, "`}` after the index")?;
+
+                Expression::ArrayIndex {
+                    array: Box::new(array),
+                    index: Box::new(index),
+                    span: token.span.to(self.last_span),
                 }
-                _ => {
-                    panic!(
-                        "Unexpected token: {} at column: {} line: {}",
-                        token.lexeme, token.column, token.line
+            }
+            _ => {
+                // Put the token back for `synchronize`, it may start the next statement
+                self.unread(token);
+
+                return Err(self.unexpected("expression"));
+            }
+        };
+
+        Ok(expression)
+    }
+
+    /// Parses the entries of an object literal after the `(` at `start`. `first_key` is the key
+    /// of the first entry when it has already been parsed as an operand.
+    ///
+    /// A single name in parentheses is a parenthesized expression, so `(a)` is `a`, while
+    /// `(a, b)` is the shorthand for `(a: a, b: b)`. A one-entry shorthand object is written
+    /// `(a: a)`.
+    fn parse_object(
+        &mut self,
+        start: Span,
+        mut first_key: Option<Expression>,
+    ) -> Result<Expression, Diagnostic> {
+        let mut entries: Vec<ObjectEntry> = Vec::new();
+        let mut names: HashMap<String, Span> = HashMap::new();
+
+        loop {
+            let key = match first_key.take() {
+                Some(key) => key,
+                None if self.eat(TokenKind::RPar)? => break,
+                None => self.parse_operand()?,
+            };
+
+            let entry = self.parse_object_entry(key)?;
+
+            if let ObjectKey::Identifier(name) | ObjectKey::String(name) = &entry.key {
+                // Reported without failing the object, the rest of it is still worth checking
+                if let Some(first) = names.insert(name.clone(), entry.span) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("duplicate key `{name}` in object"), entry.span)
+                            .with_label("redefined here")
+                            .with_note(format!(
+                                "`{name}` is first defined at line {}, column {}",
+                                first.line, first.column
+                            )),
                     );
                 }
             }
+
+            entries.push(entry);
+
+            if !self.eat(TokenKind::Comma)? {
+                self.expect(TokenKind::RPar, "`,` or `)`")?;
+                break;
+            }
+        }
+
+        Ok(Expression::ObjectExpression(
+            entries,
+            start.to(self.last_span),
+        ))
+    }
+
+    /// Parses the rest of an object entry whose key has already been parsed as an operand: a
+    /// name, a string, or a `[computed]` key, followed by `: value`. A name alone is shorthand
+    /// for `name: name`.
+    fn parse_object_entry(&mut self, key: Expression) -> Result<ObjectEntry, Diagnostic> {
+        let key_span = key.span();
+
+        let key = match key {
+            Expression::Identifier(name, span) if !self.check(TokenKind::Colon)? => {
+                return Ok(ObjectEntry {
+                    key: ObjectKey::Identifier(name.clone()),
+                    value: Expression::Identifier(name, span),
+                    span,
+                });
+            }
+            Expression::Identifier(name, _) => ObjectKey::Identifier(name),
+            Expression::Value(Value::Literal(name), _) => ObjectKey::String(name),
+            Expression::ArrayExpression(mut items, _) if items.len() == 1 => {
+                ObjectKey::Computed(items.remove(0))
+            }
+            key => {
+                return Err(Diagnostic::error("expected property name", key.span())
+                    .with_help("use a name, a string, or a computed key like `[key]: value`"));
+            }
+        };
+
+        self.expect(TokenKind::Colon, "`:` after the property name")?;
+
+        let value = self.parse_expression()?;
+
+        Ok(ObjectEntry {
+            key,
+            span: key_span.to(value.span()),
+            value,
+        })
+    }
+
+    /// Parses the `{function: argument, ...}` part of a call, after the `$` at `start`. The
+    /// argument list may be left out entirely to call a function without arguments.
+    fn parse_call(&mut self, start: Span) -> Result<Expression, Diagnostic> {
+        let mut arguments: Vec<Expression> = Vec::new();
+
+        self.expect(TokenKind::LBrace, "`{` after `$`")?;
+
+        let function = self.parse_expression()?;
+
+        if self.eat(TokenKind::Colon)? {
+            while !self.eat(TokenKind::RBrace)? {
+                arguments.push(self.parse_expression()?);
+
+                if !self.eat(TokenKind::Comma)? {
+                    self.expect(TokenKind::RBrace, "`,` or `}` after the argument")?;
+                    break;
+                }
+            }
+        } else {
+            self.expect(TokenKind::RBrace, "`:` or `}` after the function")?;
+        }
+
+        // `${f:()}` is the legacy spelling of a call without arguments
+        if let [Expression::ObjectExpression(properties, _)] = arguments.as_slice()
+            && properties.is_empty()
+        {
+            arguments.clear();
         }
 
-        expression
+        Ok(Expression::FunctionCall {
+            function: Box::new(function),
+            arguments,
+            span: start.to(self.last_span),
+        })
     }
 
-    fn parse_vars(&mut self, token: Token) -> Vec<Variable> {
+    /// Converts a number token to its value following JavaScript's rules: decimals and
+    /// exponents, `0x`/`0o`/`0b` prefixes, `_` separators between digits and the `n` suffix of
+    /// BigInt literals.
+    fn parse_number(&self, token: &Token) -> Result<Value, Diagnostic> {
+        let invalid = || {
+            Diagnostic::error(
+                format!("invalid number literal `{}`", token.lexeme),
+                token.span,
+            )
+        };
+        let out_of_range = || {
+            Diagnostic::error(
+                format!("number literal `{}` is out of range", token.lexeme),
+                token.span,
+            )
+            .with_note("numbers are 64-bit floats, so they must be below 1.8e308")
+        };
+
+        let (literal, bigint) = match token.lexeme.strip_suffix('n') {
+            Some(literal) => (literal, true),
+            None => (token.lexeme.as_str(), false),
+        };
+
+        let radix = match literal.get(..2) {
+            Some("0x" | "0X") => 16,
+            Some("0o" | "0O") => 8,
+            Some("0b" | "0B") => 2,
+            _ => 10,
+        };
+
+        let chars: Vec<char> = literal.chars().collect();
+
+        for (i, c) in chars.iter().enumerate() {
+            if *c == '_'
+                && !(i > 0
+                    && chars[i - 1].is_digit(radix)
+                    && chars.get(i + 1).is_some_and(|c| c.is_digit(radix)))
+            {
+                return Err(invalid().with_help("`_` is only allowed between two digits"));
+            }
+        }
+
+        let literal: String = chars.into_iter().filter(|c| *c != '_').collect();
+
+        if radix != 10 {
+            let digits = &literal[2..];
+
+            if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+                return Err(invalid());
+            }
+
+            if bigint {
+                return Ok(Value::BigInt(literal));
+            }
+
+            // Integers too large for u128 are accumulated as floats, which is precise enough
+            // since f64 only keeps 53 bits of them anyway
+            let number = match u128::from_str_radix(digits, radix) {
+                Ok(number) => number as f64,
+                Err(_) => digits.chars().fold(0.0, |number, c| {
+                    number * radix as f64 + c.to_digit(radix).unwrap_or(0) as f64
+                }),
+            };
+
+            if !number.is_finite() {
+                return Err(out_of_range());
+            }
+
+            return Ok(Value::Number(number));
+        }
+
+        let (mantissa, exponent) = match literal.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+            None => (literal.as_str(), None),
+        };
+        let (integer, fraction) = match mantissa.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (mantissa, None),
+        };
+
+        let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        let well_formed = (is_digits(integer) || integer.is_empty() && fraction.is_some())
+            && fraction.is_none_or(is_digits)
+            && exponent.is_none_or(|exponent| {
+                is_digits(exponent.strip_prefix(['+', '-']).unwrap_or(exponent))
+            });
+
+        if !well_formed {
+            return Err(invalid());
+        }
+
+        if integer.len() > 1 && integer.starts_with('0') {
+            return Err(invalid().with_help("leading zeros are not allowed, use `0o` for octal"));
+        }
+
+        if bigint {
+            if fraction.is_some() || exponent.is_some() {
+                return Err(invalid().with_help("BigInt literals must be integers"));
+            }
+
+            return Ok(Value::BigInt(literal));
+        }
+
+        match literal.parse::<f64>() {
+            Ok(number) if number.is_finite() => Ok(Value::Number(number)),
+            _ => Err(out_of_range()),
+        }
+    }
+
+    fn parse_vars(&mut self, token: &Token) -> Result<Vec<Variable>, Diagnostic> {
         let mut vars: Vec<Variable> = Vec::new();
 
-        if let Some(token) = self.lexer.next_token()
-            && token.kind == TokenKind::LBrace
+        self.expect(TokenKind::LBrace, &format!("`{{` after `{}`", token.lexeme))?;
+
+        while !self.eat(TokenKind::RBrace)? {
+            let name = self.expect(TokenKind::Identifier, "variable name")?;
+
+            self.expect(TokenKind::Colon, "`:` after the variable name")?;
+
+            vars.push(Variable(name.lexeme, self.parse_expression()?));
+
+            if !self.eat(TokenKind::Comma)? {
+                self.expect(TokenKind::RBrace, "`,` or `}`")?;
+                break;
+            }
+        }
+
+        Ok(vars)
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, Diagnostic> {
+        let token = match self.peeked.take() {
+            Some(token) => Some(token),
+            None => self.lexer.next_token()?,
+        };
+
+        if let Some(token) = &token {
+            self.last_span = token.span;
+            self.last_kind = Some(token.kind.clone());
+
+            match token.kind {
+                TokenKind::LBrace => self.open_braces += 1,
+                TokenKind::RBrace => self.open_braces = self.open_braces.saturating_sub(1),
+                _ => {}
+            }
+        }
+
+        Ok(token)
+    }
+
+    /// Puts back the token last returned by `next_token`.
+    fn unread(&mut self, token: Token) {
+        match token.kind {
+            TokenKind::LBrace => self.open_braces -= 1,
+            TokenKind::RBrace => self.open_braces += 1,
+            _ => {}
+        }
+
+        self.peeked = Some(token);
+    }
+
+    fn peek_token(&mut self) -> Result<Option<&Token>, Diagnostic> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.next_token()?;
+        }
+
+        Ok(self.peeked.as_ref())
+    }
+
+    /// Checks whether the next token is of the given kind without consuming it.
+    fn check(&mut self, kind: TokenKind) -> Result<bool, Diagnostic> {
+        Ok(self.peek_token()?.is_some_and(|token| token.kind == kind))
+    }
+
+    /// Consumes the next token if it is of the given kind.
+    fn eat(&mut self, kind: TokenKind) -> Result<bool, Diagnostic> {
+        if self.check(kind)? {
+            self.next_token()?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Consumes the next token, failing without consuming it if it is not of the given kind.
+    fn expect(&mut self, kind: TokenKind, expected: &str) -> Result<Token, Diagnostic> {
+        if self.check(kind.clone())?
+            && let Some(token) = self.next_token()?
         {
-            let mut current_name: Option<String> = None;
+            return Ok(token);
+        }
 
-            while let Some(token) = self.lexer.next_token() {
-                match token.kind {
-                    TokenKind::RBrace => {
-                        while let Some(token) = self.lexer.next_token()
-                            && token.kind == TokenKind::RBrace
-                        {}
+        // A missing `;` is best reported where it should have been
+        if kind == TokenKind::Semicolon {
+            let found = match self.peek_token()? {
+                Some(token) => format!("`{}`", token.lexeme),
+                None => String::from("end of file"),
+            };
 
-                        break;
-                    }
-                    TokenKind::Identifier => {
-                        current_name = Some(token.lexeme);
-                    }
-                    TokenKind::Colon => {
-                        let expression = self.parse_expression();
+            return Err(Diagnostic::error(
+                format!("expected {expected}, found {found}"),
+                self.after_last_token(),
+            )
+            .with_label("add `;` here"));
+        }
+
+        Err(self.unexpected(expected))
+    }
+
+    fn unexpected(&mut self, expected: &str) -> Diagnostic {
+        match self.peek_token() {
+            Ok(Some(token)) => Diagnostic::error(
+                format!("expected {expected}, found `{}`", token.lexeme),
+                token.span,
+            ),
+            Ok(None) => Diagnostic::error(
+                format!("expected {expected}, found end of file"),
+                self.after_last_token(),
+            ),
+            Err(diagnostic) => diagnostic,
+        }
+    }
 
-                        if let Some(name) = current_name.take() {
-                            vars.push(Variable(name, expression));
-                        }
+    /// Returns an empty span just after the last consumed token.
+    fn after_last_token(&self) -> Span {
+        Span {
+            start_byte: self.last_span.end_byte,
+            line: self.last_span.end_line,
+            column: self.last_span.end_column,
+            ..self.last_span
+        }
+    }
+
+    /// Skips tokens until a likely statement boundary: after a `;`, before a statement
+    /// keyword or before the `}` closing the current block. `depth` is the number of braces
+    /// the failed statement left open, none of which opens a block: a block that was entered
+    /// is always parsed up to its `}`.
+    ///
+    /// Braces that do not open a block, as in `let {` or `+{`, are given up on at the next `;`
+    /// or statement keyword, so that a missing `}` does not swallow the rest of the file. The
+    /// blocks of a statement skipped over are skipped whole.
+    fn synchronize(&mut self, depth: usize) {
+        // Whether each brace left open opens a block
+        let mut open: Vec<bool> = vec![false; depth];
+        let mut previous = self.last_kind.clone();
+
+        loop {
+            let kind = match self.peek_token() {
+                Ok(Some(token)) => token.kind.clone(),
+                Ok(None) => return,
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    continue;
+                }
+            };
+
+            match kind {
+                TokenKind::Fn
+                | TokenKind::Let
+                | TokenKind::Set
+                | TokenKind::Const
+                | TokenKind::When
+                | TokenKind::OrWhen
+                | TokenKind::Or
+                | TokenKind::Loop
+                | TokenKind::Each
+                | TokenKind::Break
+                | TokenKind::Continue
+                | TokenKind::Return => {
+                    close_expression_braces(&mut open);
+
+                    if open.is_empty() {
+                        return;
                     }
-                    TokenKind::Comma => {}
-                    _ => {
-                        panic!(
-                            "Unexpected token {:?} at {}:{}",
-                            token.lexeme, token.line, token.column
-                        );
+                }
+                // At the top level there is no block for a stray `}` to close
+                TokenKind::RBrace if open.is_empty() && self.block_depth > 1 => return,
+                TokenKind::RBrace => {
+                    open.pop();
+                }
+                TokenKind::LBrace => open.push(opens_block(previous.as_ref())),
+                TokenKind::Semicolon => {
+                    close_expression_braces(&mut open);
+
+                    if open.is_empty() {
+                        let _ = self.next_token();
+                        return;
                     }
                 }
+                _ => {}
             }
-        } else {
-            panic!(
-                "Expected '{{' after '{}' at column {}",
-                token.lexeme, token.column
-            );
+
+            previous = Some(kind);
+
+            let _ = self.next_token();
         }
+    }
+}
+
+/// Forgets the braces opened since the innermost block, at the end of a statement.
+fn close_expression_braces(open: &mut Vec<bool>) {
+    while open.last() == Some(&false) {
+        open.pop();
+    }
+}
+
+/// Whether a `{` following a token of kind `previous` opens a block, as in `fn name {`,
+/// `fn {`, `when:{...} {` or `or {`, rather than a list like `let {` or `+{`.
+fn opens_block(previous: Option<&TokenKind>) -> bool {
+    matches!(
+        previous,
+        Some(TokenKind::Identifier | TokenKind::RBrace | TokenKind::Fn | TokenKind::Or)
+    )
+}
+
+/// Accesses `property` on `object`, spanning from the object to `end`.
+fn property_access(object: Expression, property: Property, end: Span) -> Expression {
+    Expression::PropertyAccess {
+        span: object.span().to(end),
+        object: Box::new(object),
+        property,
+    }
+}
 
-        vars
+/// Whether `expression` is the number literal `0` or `-0`.
+fn is_zero(expression: &Expression) -> bool {
+    match expression {
+        Expression::Value(Value::Number(number), _) => *number == 0.0,
+        Expression::Unary {
+            op: UnaryOperator::Negate,
+            operand,
+            ..
+        } => is_zero(operand),
+        _ => false,
     }
 }
+
+/// Maps the tokens usable as binary operators, in both prefix and infix form, to the operator.
+fn binary_operator(kind: &TokenKind) -> Option<Operator> {
+    let op = match kind {
+        TokenKind::Plus => Operator::Plus,
+        TokenKind::Minus => Operator::Minus,
+        TokenKind::Times => Operator::Times,
+        TokenKind::Divided => Operator::Divided,
+        TokenKind::Modulo => Operator::Modulo,
+        TokenKind::Power => Operator::Power,
+        TokenKind::Gt => Operator::Gt,
+        TokenKind::Lt => Operator::Lt,
+        TokenKind::GtEqual => Operator::GtEqual,
+        TokenKind::LtEqual => Operator::LtEqual,
+        TokenKind::Equal => Operator::Equal,
+        TokenKind::NotEqual => Operator::NotEqual,
+        TokenKind::LogicalAnd => Operator::And,
+        TokenKind::LogicalOr => Operator::Or,
+        _ => return None,
+    };
+
+    Some(op)
+}
@@ -0,0 +1,241 @@
+use crate::lexer::Span;
+use crate::transpiler::escape_string;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, Clone, Default)]
+pub struct SourceMapOptions {
+    /// Name of the Klynt file, listed in `sources`.
+    pub source_name: String,
+    /// The Klynt source, embedded as `sourcesContent` so devtools do not have to fetch it.
+    pub source_content: Option<String>,
+    /// Name of the generated JavaScript file.
+    pub file: Option<String>,
+    /// URL of the map written in the `//# sourceMappingURL` comment. When `None`, the map is
+    /// inlined in the comment as a data URL.
+    pub url: Option<String>,
+}
+
+/// A position in the generated code mapped back to the Klynt source. Lines and columns are
+/// zero-based, and columns on both sides are counted in UTF-16 code units.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Mapping {
+    pub generated_line: usize,
+    pub generated_column: usize,
+    pub source_line: usize,
+    pub source_column: usize,
+    pub name: Option<String>,
+}
+
+impl Mapping {
+    pub fn new(generated_line: usize, generated_column: usize, span: Span) -> Self {
+        Self {
+            generated_line,
+            generated_column,
+            source_line: span.line.saturating_sub(1),
+            source_column: span.utf16_column.saturating_sub(1),
+            name: None,
+        }
+    }
+}
+
+/// A Source Map v3, see https://tc39.es/source-map/.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceMap {
+    pub file: Option<String>,
+    pub sources: Vec<String>,
+    pub sources_content: Vec<Option<String>>,
+    pub names: Vec<String>,
+    /// The VLQ encoded mappings.
+    pub mappings: String,
+}
+
+impl SourceMap {
+    /// Builds the map of a single source from mappings sorted by generated position.
+    pub(crate) fn from_mappings(options: &SourceMapOptions, mappings: &[Mapping]) -> Self {
+        let mut names: Vec<String> = Vec::new();
+        let mut encoded = String::new();
+
+        let mut line = 0;
+        // Every field but the generated column is relative to the previous segment of the file
+        let mut previous_column = 0;
+        let mut previous_source_line = 0;
+        let mut previous_source_column = 0;
+        let mut previous_name = 0;
+
+        for (index, mapping) in mappings.iter().enumerate() {
+            if mapping.generated_line > line {
+                encoded.push_str(&";".repeat(mapping.generated_line - line));
+                line = mapping.generated_line;
+                previous_column = 0;
+            } else if index > 0 {
+                encoded.push(',');
+            }
+
+            encode_vlq(
+                &mut encoded,
+                mapping.generated_column as i64 - previous_column as i64,
+            );
+            // Index of the only source
+            encode_vlq(&mut encoded, 0);
+            encode_vlq(
+                &mut encoded,
+                mapping.source_line as i64 - previous_source_line as i64,
+            );
+            encode_vlq(
+                &mut encoded,
+                mapping.source_column as i64 - previous_source_column as i64,
+            );
+
+            if let Some(name) = &mapping.name {
+                let name_index = match names.iter().position(|known| known == name) {
+                    Some(position) => position,
+                    None => {
+                        names.push(name.clone());
+                        names.len() - 1
+                    }
+                };
+
+                encode_vlq(&mut encoded, name_index as i64 - previous_name as i64);
+                previous_name = name_index;
+            }
+
+            previous_column = mapping.generated_column;
+            previous_source_line = mapping.source_line;
+            previous_source_column = mapping.source_column;
+        }
+
+        Self {
+            file: options.file.clone(),
+            sources: vec![options.source_name.clone()],
+            sources_content: vec![options.source_content.clone()],
+            names,
+            mappings: encoded,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let strings = |strings: &[String]| {
+            strings
+                .iter()
+                .map(|string| format!("\"{}\"", escape_string(string)))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let sources_content = self
+            .sources_content
+            .iter()
+            .map(|content| match content {
+                Some(content) => format!("\"{}\"", escape_string(content)),
+                None => String::from("null"),
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let file = match &self.file {
+            Some(file) => format!("\"file\":\"{}\",", escape_string(file)),
+            None => String::new(),
+        };
+
+        format!(
+            "{{\"version\":3,{file}\"sources\":[{}],\"sourcesContent\":[{sources_content}],\"names\":[{}],\"mappings\":\"{}\"}}",
+            strings(&self.sources),
+            strings(&self.names),
+            self.mappings
+        )
+    }
+
+    /// Returns the map as a `data:` URL, to inline it in the generated code.
+    pub fn to_data_url(&self) -> String {
+        format!(
+            "data:application/json;charset=utf-8;base64,{}",
+            encode_base64(self.to_json().as_bytes())
+        )
+    }
+}
+
+/// Appends `value` as a Base64 VLQ: the sign goes in the lowest bit, then groups of five bits
+/// from the least significant, each but the last flagged with a continuation bit.
+fn encode_vlq(output: &mut String, value: i64) {
+    let mut vlq = if value < 0 {
+        (value.unsigned_abs() << 1) | 1
+    } else {
+        (value as u64) << 1
+    };
+
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+
+        output.push(BASE64[digit as usize] as char);
+
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+            group | (*byte as u32) << (16 - index * 8)
+        });
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(BASE64[(group >> (18 - index * 6)) as usize & 0b111111] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vlq(value: i64) -> String {
+        let mut output = String::new();
+
+        encode_vlq(&mut output, value);
+        output
+    }
+
+    #[test]
+    fn vlq_single_digits() {
+        assert_eq!(vlq(0), "A");
+        assert_eq!(vlq(1), "C");
+        assert_eq!(vlq(-1), "D");
+        assert_eq!(vlq(15), "e");
+        assert_eq!(vlq(-15), "f");
+    }
+
+    #[test]
+    fn vlq_several_digits() {
+        assert_eq!(vlq(16), "gB");
+        assert_eq!(vlq(-16), "hB");
+        assert_eq!(vlq(123), "2H");
+        assert_eq!(vlq(-1000), "x+B");
+        assert_eq!(vlq(100_000), "gqjG");
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"Man"), "TWFu");
+        assert_eq!(encode_base64(b"Ma"), "TWE=");
+        assert_eq!(encode_base64(b"M"), "TQ==");
+        assert_eq!(encode_base64(b"light w"), "bGlnaHQgdw==");
+        assert_eq!(encode_base64(&[0xfb, 0xff]), "+/8=");
+    }
+}
@@ -1,208 +1,987 @@
 use crate::ast::ASTNode;
 use crate::ast::Expression;
+use crate::ast::ObjectKey;
 use crate::ast::Operator;
+use crate::ast::Property;
+use crate::ast::UnaryOperator;
 use crate::ast::Value;
+use crate::ast::Variable;
+use crate::diagnostic::Diagnostic;
+use crate::lexer::Span;
+use crate::mangle::Mangler;
+use crate::mangle::block_declarations;
+use crate::sourcemap::Mapping;
+use crate::sourcemap::SourceMap;
+use crate::sourcemap::SourceMapOptions;
+
+/// Precedence of arrow functions, lower than every operator.
+const ARROW_PRECEDENCE: u8 = 0;
+/// Precedence of `-a` and `!a`, higher than every binary operator.
+const PREFIX_PRECEDENCE: u8 = 8;
+/// Precedence of calls and member accesses.
+const POSTFIX_PRECEDENCE: u8 = 9;
+const PRIMARY_PRECEDENCE: u8 = 10;
+
+/// Name of the function checking array accesses, written at the top of the code when runtime
+/// checks are enabled.
+const INDEX_CHECK: &str = "__klynt_index";
+
+/// The ECMAScript version the generated code must run on. Only the versions changing what can be
+/// emitted are listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Target {
+    /// `**` is written as `Math.pow`, BigInt literals are rejected.
+    Es2015,
+    /// BigInt literals are rejected.
+    Es2016,
+    #[default]
+    Es2020,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ModuleFormat {
+    /// A plain script, exporting nothing.
+    #[default]
+    Script,
+    /// Exports the top-level functions and variables with `export`.
+    EsModule,
+    /// Exports the top-level functions and variables through `module.exports`.
+    CommonJs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputStyle {
+    /// One statement per line, without indentation.
+    #[default]
+    Compact,
+    /// Indented blocks and spaces around operators.
+    Pretty,
+    /// No optional whitespace, with the variables and parameters declared inside functions
+    /// renamed to short names.
+    Minified,
+}
+
+#[derive(Debug, Clone)]
+pub struct TranspileOptions {
+    pub target: Target,
+    pub module: ModuleFormat,
+    /// Emit `===` and `!==` instead of `==` and `!=` for Klynt's `=` and `!=`.
+    pub strict_equality: bool,
+    pub style: OutputStyle,
+    /// Number of spaces per indentation level in pretty mode.
+    pub indent_width: usize,
+    /// Generates a source map along with the code when set.
+    pub source_map: Option<SourceMapOptions>,
+    /// Runs the code in strict mode and makes `@{array, index}` throw a `RangeError` when the
+    /// index is missing from the array, instead of returning `undefined`.
+    pub runtime_checks: bool,
+}
+
+impl Default for TranspileOptions {
+    fn default() -> Self {
+        Self {
+            target: Target::default(),
+            module: ModuleFormat::default(),
+            strict_equality: false,
+            style: OutputStyle::default(),
+            indent_width: 4,
+            source_map: None,
+            runtime_checks: false,
+        }
+    }
+}
+
+impl TranspileOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_module(mut self, module: ModuleFormat) -> Self {
+        self.module = module;
+        self
+    }
+
+    pub fn with_strict_equality(mut self, strict_equality: bool) -> Self {
+        self.strict_equality = strict_equality;
+        self
+    }
+
+    pub fn with_style(mut self, style: OutputStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_indent_width(mut self, indent_width: usize) -> Self {
+        self.indent_width = indent_width;
+        self
+    }
+
+    pub fn with_source_map(mut self, source_map: SourceMapOptions) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+
+    pub fn with_runtime_checks(mut self, runtime_checks: bool) -> Self {
+        self.runtime_checks = runtime_checks;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranspileOutput {
+    /// The JavaScript code, ending with a `//# sourceMappingURL` comment when a source map is
+    /// generated.
+    pub code: String,
+    pub source_map: Option<SourceMap>,
+}
 
 pub struct Transpiler {
     nodes: Vec<ASTNode>,
+    options: TranspileOptions,
+}
+
+/// The generated code, along with the current position in it and the source positions the
+/// code written so far maps back to.
+#[derive(Default)]
+struct Output {
+    code: String,
+    line: usize,
+    column: usize,
+    mappings: Vec<Mapping>,
+    style: OutputStyle,
+    /// Spaces per indentation level, zero unless pretty printing.
+    indent_width: usize,
+    depth: usize,
+    /// Whether the indentation of the current line is still to be written.
+    line_start: bool,
+    /// Renames local names when minifying.
+    mangler: Option<Mangler>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Output {
+    fn new(options: &TranspileOptions, nodes: &[ASTNode]) -> Self {
+        let style = options.style;
+
+        Self {
+            style,
+            indent_width: if style == OutputStyle::Pretty {
+                options.indent_width
+            } else {
+                0
+            },
+            line_start: true,
+            mangler: (style == OutputStyle::Minified).then(|| Mangler::new(nodes)),
+            ..Self::default()
+        }
+    }
+
+    /// Writes `text`, indenting the lines it starts.
+    fn push(&mut self, text: &str) {
+        // `a - -b` must not be written as the decrement `a--b`
+        if let (Some(last), Some(first)) = (self.code.chars().last(), text.chars().next())
+            && matches!(last, '+' | '-')
+            && last == first
+        {
+            self.push(" ");
+        }
+
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 0;
+                self.line_start = true;
+            } else {
+                self.indent();
+                self.column += c.len_utf16();
+            }
+
+            self.code.push(c);
+        }
+    }
+
+    fn indent(&mut self) {
+        if self.line_start {
+            let indentation = self.depth * self.indent_width;
+
+            self.code.push_str(&" ".repeat(indentation));
+            self.column += indentation;
+            self.line_start = false;
+        }
+    }
+
+    /// Writes a space in pretty mode only.
+    fn space(&mut self) {
+        if self.style == OutputStyle::Pretty {
+            self.push(" ");
+        }
+    }
+
+    /// Writes a space that only helps readability, left out of minified code.
+    fn readable_space(&mut self) {
+        if self.style != OutputStyle::Minified {
+            self.push(" ");
+        }
+    }
+
+    fn newline(&mut self) {
+        if self.style != OutputStyle::Minified {
+            self.push("\n");
+        }
+    }
+
+    /// Writes a binary operator, surrounded by spaces in pretty mode.
+    fn operator(&mut self, operator: &str) {
+        self.space();
+        self.push(operator);
+        self.space();
+    }
+
+    /// Writes the separator between the items of a list.
+    fn comma(&mut self) {
+        self.push(",");
+        self.space();
+    }
+
+    fn open_block(&mut self) {
+        self.push("{");
+        self.newline();
+        self.depth += 1;
+    }
+
+    fn close_block(&mut self) {
+        self.depth -= 1;
+        self.push("}");
+    }
+
+    /// Returns the name `name` is written as.
+    fn name(&self, name: &str) -> String {
+        match &self.mangler {
+            Some(mangler) => mangler.name(name),
+            None => name.to_string(),
+        }
+    }
+
+    fn enter_function<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) {
+        if let Some(mangler) = &mut self.mangler {
+            mangler.enter_function(names);
+        }
+    }
+
+    fn enter_block<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) {
+        if let Some(mangler) = &mut self.mangler {
+            mangler.enter_block(names);
+        }
+    }
+
+    fn exit_scope(&mut self) {
+        if let Some(mangler) = &mut self.mangler {
+            mangler.exit();
+        }
+    }
+
+    /// Maps the code written next to `span`.
+    fn map(&mut self, span: Span) {
+        self.add_mapping(Mapping::new(self.line, self.column, span));
+    }
+
+    /// Maps the code written next to `span`, where the identifier `name` is written.
+    fn map_name(&mut self, span: Span, name: &str) {
+        self.add_mapping(Mapping {
+            name: Some(name.to_string()),
+            ..Mapping::new(self.line, self.column, span)
+        });
+    }
+
+    /// Adds a mapping, replacing the last one when both start at the same generated position,
+    /// such as a statement starting with an identifier.
+    fn add_mapping(&mut self, mut mapping: Mapping) {
+        // The code is written after the indentation
+        self.indent();
+        mapping.generated_column = self.column;
+
+        if let Some(last) = self.mappings.last_mut()
+            && (last.generated_line, last.generated_column)
+                == (mapping.generated_line, mapping.generated_column)
+        {
+            *last = mapping;
+        } else {
+            self.mappings.push(mapping);
+        }
+    }
 }
 
 impl Transpiler {
     pub fn new(nodes: Vec<ASTNode>) -> Self {
-        Self { nodes }
+        Self::with_options(nodes, TranspileOptions::default())
+    }
+
+    pub fn with_options(nodes: Vec<ASTNode>, options: TranspileOptions) -> Self {
+        Self { nodes, options }
+    }
+
+    pub fn transpile(&self) -> Result<String, Vec<Diagnostic>> {
+        self.transpile_output().map(|output| output.code)
     }
 
-    pub fn transpile(&self) -> String {
-        let mut code = String::new();
+    /// Transpiles the nodes, along with a source map when the options ask for one. The map is
+    /// referenced from a `//# sourceMappingURL` comment at the end of the code.
+    pub fn transpile_output(&self) -> Result<TranspileOutput, Vec<Diagnostic>> {
+        let mut out = Output::new(&self.options, &self.nodes);
 
-        for node in &self.nodes {
-            code.push_str(Transpiler::transpile_node(node).as_str());
+        self.transpile_program(&mut out);
+
+        if !out.diagnostics.is_empty() {
+            return Err(out.diagnostics);
+        }
+
+        let Some(options) = &self.options.source_map else {
+            return Ok(TranspileOutput {
+                code: out.code,
+                source_map: None,
+            });
+        };
+
+        let map = SourceMap::from_mappings(options, &out.mappings);
+        let url = match &options.url {
+            Some(url) => url.clone(),
+            None => map.to_data_url(),
+        };
+
+        // The comment must start on its own line
+        if !out.line_start {
+            out.push("\n");
+        }
+
+        out.push(&format!("//# sourceMappingURL={url}\n"));
+
+        Ok(TranspileOutput {
+            code: out.code,
+            source_map: Some(map),
+        })
+    }
+
+    /// Writes the top-level nodes, along with what the module format and the runtime checks
+    /// need around them.
+    fn transpile_program(&self, out: &mut Output) {
+        if self.options.runtime_checks {
+            // Modules are always strict
+            if self.options.module != ModuleFormat::EsModule {
+                out.push("\"use strict\";");
+                out.newline();
+            }
+
+            out.push(&format!(
+                "function {INDEX_CHECK}(a,i,at){{if(!(i in Object(a)))throw new RangeError(\"index \"+String(i)+\" out of range at \"+at);return a[i];}}"
+            ));
+            out.newline();
+        }
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            if self.options.module == ModuleFormat::EsModule
+                && matches!(
+                    node,
+                    ASTNode::FunctionDeclaration { .. }
+                        | ASTNode::VariableDeclaration { .. }
+                        | ASTNode::ConstDeclaration { .. }
+                )
+            {
+                out.push("export ");
+            }
+
+            self.transpile_node(node, out);
+            self.end_statement(node, index + 1 == self.nodes.len(), out);
         }
 
-        code
+        let exports = block_declarations(&self.nodes);
+
+        if self.options.module == ModuleFormat::CommonJs && !exports.is_empty() {
+            out.push("module.exports");
+            out.operator("=");
+            out.push("{");
+            out.space();
+
+            for (index, name) in exports.iter().enumerate() {
+                if index > 0 {
+                    out.comma();
+                }
+
+                out.push(&format!("{name}:"));
+                out.readable_space();
+                out.push(name);
+            }
+
+            out.space();
+            out.push("};");
+            out.newline();
+        }
     }
 
-    fn transpile_node(node: &ASTNode) -> String {
-        let mut code = String::new();
+    fn transpile_node(&self, node: &ASTNode, out: &mut Output) {
+        out.map(node.span());
 
         match node {
             ASTNode::FunctionDeclaration {
-                start: _,
+                span: _,
                 name,
+                parameters,
                 content,
             } => {
-                let body = Transpiler::transpile_block(content);
+                let name = out.name(name);
+                let parameters = match parameters {
+                    Some(parameters) => parameters.iter().map(String::as_str).collect(),
+                    None => vec!["param"],
+                };
 
-                code.push_str(format!("function {name}(param) {{\n{body}\n}}\n").as_str());
-            }
-            ASTNode::VariableDeclaration { start: _, vars } => {
-                let vars = vars
-                    .iter()
-                    .map(|v| format!("{}={}", v.0, Transpiler::transpile_expression(&v.1)))
-                    .collect::<Vec<_>>()
-                    .join(",");
+                out.enter_function(
+                    parameters
+                        .iter()
+                        .copied()
+                        .chain(block_declarations(content)),
+                );
+
+                let parameters = self.parameters(&parameters, out);
+
+                out.push(&format!("function {name}({parameters})"));
+                out.readable_space();
+                out.open_block();
+                self.transpile_block(content, out);
+
+                // Compact output keeps a blank line before and after the closing brace
+                if out.style == OutputStyle::Compact {
+                    out.push("\n");
+                    out.close_block();
+                    out.push("\n");
+                } else {
+                    out.close_block();
+                }
 
-                code.push_str(format!("let {vars};").as_str());
+                out.exit_scope();
+            }
+            ASTNode::VariableDeclaration { span: _, vars } => {
+                out.push("let ");
+                self.transpile_vars(vars, out);
+                out.push(";");
             }
             ASTNode::Expression(expression) => {
-                code.push_str(
-                    format!("{};", Transpiler::transpile_expression(expression)).as_str(),
-                );
+                // A statement starting with `{` would be a block
+                if starts_with_object(expression) {
+                    out.push("(");
+                    self.transpile_expression(expression, out);
+                    out.push(")");
+                } else {
+                    self.transpile_expression(expression, out);
+                }
+
+                out.push(";");
             }
-            ASTNode::VariableSetting { start: _, vars } => {
-                let vars = vars
-                    .iter()
-                    .map(|v| format!("{}={}", v.0, Transpiler::transpile_expression(&v.1)))
-                    .collect::<Vec<_>>()
-                    .join(";");
+            ASTNode::VariableSetting { span: _, vars } => {
+                // One assignment per line in pretty mode
+                let separator = match out.style {
+                    OutputStyle::Pretty => ";\n",
+                    _ => ";",
+                };
 
-                code.push_str(format!("{vars};").as_str());
+                self.transpile_assignments(vars, separator, out);
+                out.push(";");
             }
             ASTNode::ReturnExpression {
-                start: _,
+                span: _,
                 expression,
             } => {
-                code.push_str(
-                    format!("return {};", Transpiler::transpile_expression(expression)).as_str(),
-                );
+                out.push("return ");
+                self.transpile_expression(expression, out);
+                out.push(";");
+            }
+            ASTNode::ConstDeclaration { span: _, vars } => {
+                out.push("const ");
+                self.transpile_vars(vars, out);
+                out.push(";");
             }
-            ASTNode::ConstDeclaration { start: _, vars } => {
-                let vars = vars
-                    .iter()
-                    .map(|v| format!("{}={}", v.0, Transpiler::transpile_expression(&v.1)))
-                    .collect::<Vec<_>>()
-                    .join(",");
+            ASTNode::If {
+                span: _,
+                branches,
+                else_branch,
+            } => {
+                let else_keyword = match out.style {
+                    OutputStyle::Compact => "\nelse",
+                    OutputStyle::Pretty => " else",
+                    OutputStyle::Minified => "else",
+                };
+
+                for (index, (condition, content)) in branches.iter().enumerate() {
+                    if index > 0 {
+                        out.push(else_keyword);
+                        out.push(" ");
+                    }
+
+                    out.push("if");
+                    out.readable_space();
+                    out.push("(");
+                    self.transpile_expression(condition, out);
+                    out.push(")");
+                    self.transpile_body(content, out);
+                }
 
-                code.push_str(format!("const {vars};").as_str());
+                if let Some(content) = else_branch {
+                    out.push(else_keyword);
+                    self.transpile_body(content, out);
+                }
             }
-            _ => {}
+            ASTNode::While {
+                span: _,
+                condition,
+                content,
+            } => {
+                out.push("while");
+                out.readable_space();
+                out.push("(");
+                self.transpile_expression(condition, out);
+                out.push(")");
+                self.transpile_body(content, out);
+            }
+            ASTNode::For {
+                span: _,
+                variable,
+                start,
+                end,
+                step,
+                content,
+            } => {
+                out.enter_block([variable.as_str()]);
+
+                let variable = out.name(variable);
+
+                out.push("for");
+                out.readable_space();
+                out.push(&format!("(let {variable}"));
+                out.operator("=");
+                self.transpile_expression(start, out);
+                out.push(";");
+                out.space();
+                out.push(&variable);
+
+                // Only a literal step is known to count down
+                if step.as_ref().is_some_and(is_negative_number) {
+                    out.operator(">");
+                } else {
+                    out.operator("<");
+                }
+
+                self.transpile_operand(end, Operator::Lt.precedence() + 1, out);
+                out.push(";");
+                out.space();
+                out.push(&variable);
+
+                match step {
+                    Some(step) => {
+                        out.operator("+=");
+                        self.transpile_expression(step, out);
+                    }
+                    None => out.push("++"),
+                }
+
+                out.push(")");
+                self.transpile_body(content, out);
+                out.exit_scope();
+            }
+            ASTNode::ForEach {
+                span: _,
+                item,
+                iterable,
+                content,
+            } => {
+                out.enter_block([item.as_str()]);
+
+                let item = out.name(item);
+
+                out.push("for");
+                out.readable_space();
+                out.push(&format!("(let {item} of "));
+                self.transpile_expression(iterable, out);
+                out.push(")");
+                self.transpile_body(content, out);
+                out.exit_scope();
+            }
+            ASTNode::Break { span: _ } => out.push("break;"),
+            ASTNode::Continue { span: _ } => out.push("continue;"),
+        }
+    }
+
+    /// Writes the declarators of a `let` or `const` declaration.
+    fn transpile_vars(&self, vars: &[Variable], out: &mut Output) {
+        for (index, var) in vars.iter().enumerate() {
+            if index > 0 {
+                out.comma();
+            }
+
+            self.transpile_assignment(var, out);
+        }
+    }
+
+    /// Writes the assignments of a `set` statement, separated by `separator`.
+    fn transpile_assignments(&self, vars: &[Variable], separator: &str, out: &mut Output) {
+        for (index, var) in vars.iter().enumerate() {
+            if index > 0 {
+                out.push(separator);
+            }
+
+            self.transpile_assignment(var, out);
+        }
+    }
+
+    fn transpile_assignment(&self, var: &Variable, out: &mut Output) {
+        let name = out.name(&var.0);
+
+        out.push(&name);
+        out.operator("=");
+        self.transpile_expression(&var.1, out);
+    }
+
+    fn parameters(&self, parameters: &[&str], out: &Output) -> String {
+        parameters
+            .iter()
+            .map(|parameter| out.name(parameter))
+            .collect::<Vec<_>>()
+            .join(if out.style == OutputStyle::Pretty {
+                ", "
+            } else {
+                ","
+            })
+    }
+
+    /// Writes each expression, separated by commas.
+    fn transpile_list<'a>(
+        &self,
+        expressions: impl IntoIterator<Item = &'a Expression>,
+        out: &mut Output,
+    ) {
+        for (index, expression) in expressions.into_iter().enumerate() {
+            if index > 0 {
+                out.comma();
+            }
+
+            self.transpile_expression(expression, out);
         }
+    }
 
-        code
+    fn transpile_expression(&self, expression: &Expression, out: &mut Output) {
+        self.transpile_operand(expression, ARROW_PRECEDENCE, out);
     }
 
-    fn transpile_expression(expression: &Expression) -> String {
+    /// Writes an expression used where only expressions of at least `min_precedence` can go
+    /// without parentheses.
+    fn transpile_operand(&self, expression: &Expression, min_precedence: u8, out: &mut Output) {
+        let parenthesized = self.precedence(expression) < min_precedence;
+
+        if parenthesized {
+            out.push("(");
+        }
+
+        self.transpile_unparenthesized(expression, out);
+
+        if parenthesized {
+            out.push(")");
+        }
+    }
+
+    fn transpile_unparenthesized(&self, expression: &Expression, out: &mut Output) {
         match expression {
-            Expression::Value(value) => match value {
-                Value::Literal(literal) => format!("\"{literal}\""),
-                Value::Number(number) => number.to_string(),
+            Expression::Value(value, _) => match value {
+                Value::Literal(literal) => out.push(&format!("\"{}\"", escape_string(literal))),
+                Value::Number(number) => out.push(&format_number(*number)),
+                Value::BigInt(digits) => {
+                    if self.options.target < Target::Es2020 {
+                        out.diagnostics.push(
+                            Diagnostic::error(
+                                "BigInt literals are not supported before ES2020",
+                                expression.span(),
+                            )
+                            .with_help("raise the target to ES2020 or use a number"),
+                        );
+                    }
+
+                    out.push(&format!("{digits}n"));
+                }
+                Value::Boolean(boolean) => out.push(&boolean.to_string()),
+                Value::Null => out.push("null"),
+                Value::Undefined => out.push("undefined"),
             },
-            Expression::Identifier(id) => id.to_string(),
+            Expression::Identifier(id, span) => {
+                let name = out.name(id);
+
+                out.map_name(*span, id);
+                out.push(&name);
+            }
             Expression::FunctionCall {
                 function,
-                parameter,
+                arguments,
+                ..
             } => {
-                let function = Transpiler::transpile_expression(function);
-                let mut parameter = Transpiler::transpile_expression(parameter);
+                self.transpile_operand(function, POSTFIX_PRECEDENCE, out);
+                out.push("(");
+                self.transpile_list(arguments, out);
+                out.push(")");
+            }
+            Expression::Binary {
+                left,
+                op: Operator::Power,
+                right,
+                ..
+            } if self.options.target < Target::Es2016 => {
+                out.push("Math.pow(");
+                self.transpile_list([left.as_ref(), right.as_ref()], out);
+                out.push(")");
+            }
+            Expression::Binary {
+                left, op, right, ..
+            } => {
+                let precedence = op.precedence();
+                // The left operand of `**` cannot be a unary expression
+                let (left_precedence, right_precedence) = if op.is_right_associative() {
+                    (POSTFIX_PRECEDENCE, precedence)
+                } else {
+                    (precedence, precedence + 1)
+                };
+
+                self.transpile_operand(left, left_precedence, out);
+                out.operator(match op {
+                    Operator::Plus => "+",
+                    Operator::Minus => "-",
+                    Operator::Times => "*",
+                    Operator::Divided => "/",
+                    Operator::Modulo => "%",
+                    Operator::Power => "**",
+                    Operator::Gt => ">",
+                    Operator::Lt => "<",
+                    Operator::GtEqual => ">=",
+                    Operator::LtEqual => "<=",
+                    Operator::Equal if self.options.strict_equality => "===",
+                    Operator::Equal => "==",
+                    Operator::NotEqual if self.options.strict_equality => "!==",
+                    Operator::NotEqual => "!=",
+                    Operator::And => "&&",
+                    Operator::Or => "||",
+                });
+                self.transpile_operand(right, right_precedence, out);
+            }
+            Expression::Unary { op, operand, .. } => {
+                out.push(match op {
+                    UnaryOperator::Negate => "-",
+                    UnaryOperator::Not => "!",
+                });
+                self.transpile_operand(operand, PREFIX_PRECEDENCE, out);
+            }
+            Expression::ArrayExpression(items, _) => {
+                out.push("[");
+                self.transpile_list(items.iter().map(|item| item.as_ref()), out);
+                out.push("]");
+            }
+            Expression::ObjectExpression(entries, _) => {
+                out.push("{");
 
-                if parameter == "{}" {
-                    parameter = String::new();
+                if !entries.is_empty() {
+                    out.space();
                 }
 
-                format!("{function}({parameter})")
-            }
-            Expression::Binary { left, op, right } => {
-                let left = Transpiler::transpile_expression(left);
-                let right = Transpiler::transpile_expression(right);
-
-                // TODO: handle different operators
-                format!(
-                    "({left}{}{right})",
-                    match op {
-                        Operator::Plus => "+",
-                        Operator::Minus => "-",
-                        Operator::Times => "*",
-                        Operator::Divided => "/",
-                        Operator::Gt => ">",
-                        Operator::Lt => "<",
-                        Operator::Equal => "==",
+                for (index, entry) in entries.iter().enumerate() {
+                    if index > 0 {
+                        out.comma();
                     }
-                )
-            }
-            Expression::ArrayExpression(items) => {
-                let items = items
-                    .iter()
-                    .map(|x| Transpiler::transpile_expression(x))
-                    .collect::<Vec<_>>()
-                    .join(",");
-
-                format!("[{items}]")
-            }
-            Expression::ObjectExpression(hashmap) => {
-                let obj = hashmap
-                    .iter()
-                    .map(|x| format!("{}: {}", x.0, Transpiler::transpile_expression(x.1)))
-                    .collect::<Vec<_>>()
-                    .join(",");
-
-                format!("{{{obj}}}")
-            }
-            Expression::PropertyAccess { object, property } => {
-                let property = Transpiler::transpile_expression(property);
-                format!("{object}.{property}")
-            }
-            Expression::ArrayIndex { array, index } => {
-                let array = Transpiler::transpile_expression(array);
-                let index = Transpiler::transpile_expression(index);
-
-                format!("{array}[{index}]")
-            }
-        }
-    }
-
-    fn transpile_block(nodes: &[ASTNode]) -> String {
-        let mut code = String::new();
-        let mut i = 0;
-
-        while i < nodes.len() {
-            match &nodes[i] {
-                ASTNode::WhenExpression {
-                    expression,
-                    content,
-                    ..
-                } => {
-                    code.push_str(&format!(
-                        "if ({}) {{\n",
-                        Transpiler::transpile_expression(expression)
-                    ));
-                    code.push_str(&Transpiler::transpile_block(content));
-                    code.push_str("}\n");
-
-                    i += 1;
-                    while i < nodes.len() {
-                        match &nodes[i] {
-                            ASTNode::OrWhenExpression {
-                                expression,
-                                content,
-                                ..
-                            } => {
-                                code.push_str(&format!(
-                                    "else if ({}) {{\n",
-                                    Transpiler::transpile_expression(expression)
-                                ));
-                                code.push_str(&Transpiler::transpile_block(content));
-                                code.push_str("}\n");
-                                i += 1;
-                            }
-                            ASTNode::OrExpression { content, .. } => {
-                                code.push_str("else {\n");
-                                code.push_str(&Transpiler::transpile_block(content));
-                                code.push_str("}\n");
-                                i += 1;
-                                break;
-                            }
-                            _ => break,
+
+                    match &entry.key {
+                        ObjectKey::Identifier(name) => out.push(name),
+                        ObjectKey::String(name) => {
+                            out.push(&format!("\"{}\"", escape_string(name)))
+                        }
+                        ObjectKey::Computed(key) => {
+                            out.push("[");
+                            self.transpile_expression(key, out);
+                            out.push("]");
                         }
                     }
+
+                    out.push(":");
+                    out.readable_space();
+                    self.transpile_expression(&entry.value, out);
                 }
-                other => {
-                    code.push_str(&Transpiler::transpile_node(other));
-                    code.push('\n');
-                    i += 1;
+
+                if !entries.is_empty() {
+                    out.space();
                 }
+
+                out.push("}");
             }
+            Expression::PropertyAccess {
+                object, property, ..
+            } => {
+                match object.as_ref() {
+                    // `1.toString` and `{}.a` do not parse as member expressions
+                    Expression::Value(Value::Number(_), _) | Expression::ObjectExpression(..) => {
+                        out.push("(");
+                        self.transpile_expression(object, out);
+                        out.push(")");
+                    }
+                    object => self.transpile_operand(object, POSTFIX_PRECEDENCE, out),
+                }
+
+                match property {
+                    Property::Named(name) => out.push(&format!(".{name}")),
+                    Property::Computed(key) => {
+                        out.push("[");
+                        self.transpile_expression(key, out);
+                        out.push("]");
+                    }
+                }
+            }
+            Expression::ArrayIndex { array, index, span } if self.options.runtime_checks => {
+                out.push(&format!("{INDEX_CHECK}("));
+                self.transpile_list([array.as_ref(), index.as_ref()], out);
+                out.comma();
+                out.push(&format!("\"{}:{}\")", span.line, span.column));
+            }
+            Expression::ArrayIndex { array, index, .. } => {
+                self.transpile_operand(array, POSTFIX_PRECEDENCE, out);
+                out.push("[");
+                self.transpile_expression(index, out);
+                out.push("]");
+            }
+            Expression::Function {
+                parameters,
+                content,
+                ..
+            } => {
+                let parameters: Vec<&str> = parameters.iter().map(String::as_str).collect();
+
+                out.enter_function(
+                    parameters
+                        .iter()
+                        .copied()
+                        .chain(block_declarations(content)),
+                );
+
+                let parameters = self.parameters(&parameters, out);
+
+                out.push(&format!("({parameters})"));
+                out.readable_space();
+                out.push("=>");
+                out.readable_space();
+                out.open_block();
+                self.transpile_block(content, out);
+                out.close_block();
+                out.exit_scope();
+            }
+        }
+    }
+
+    /// Returns the precedence of the expression as written in JavaScript.
+    fn precedence(&self, expression: &Expression) -> u8 {
+        match expression {
+            // Written as a call to `Math.pow`
+            Expression::Binary {
+                op: Operator::Power,
+                ..
+            } if self.options.target < Target::Es2016 => POSTFIX_PRECEDENCE,
+            Expression::Binary { op, .. } => op.precedence(),
+            Expression::Unary { .. } => PREFIX_PRECEDENCE,
+            Expression::FunctionCall { .. }
+            | Expression::PropertyAccess { .. }
+            | Expression::ArrayIndex { .. } => POSTFIX_PRECEDENCE,
+            Expression::Function { .. } => ARROW_PRECEDENCE,
+            Expression::Value(..)
+            | Expression::Identifier(..)
+            | Expression::ArrayExpression(..)
+            | Expression::ObjectExpression(..) => PRIMARY_PRECEDENCE,
+        }
+    }
+
+    /// Writes the `{...}` body of a statement, in its own scope.
+    fn transpile_body(&self, content: &[ASTNode], out: &mut Output) {
+        out.enter_block(block_declarations(content));
+        out.readable_space();
+        out.open_block();
+        self.transpile_block(content, out);
+        out.close_block();
+        out.exit_scope();
+    }
+
+    fn transpile_block(&self, nodes: &[ASTNode], out: &mut Output) {
+        for (index, node) in nodes.iter().enumerate() {
+            self.transpile_node(node, out);
+            self.end_statement(node, index + 1 == nodes.len(), out);
+        }
+    }
+
+    fn end_statement(&self, node: &ASTNode, last: bool, out: &mut Output) {
+        out.newline();
+
+        // Pretty mode separates functions from the following statements with a blank line
+        if out.style == OutputStyle::Pretty
+            && matches!(node, ASTNode::FunctionDeclaration { .. })
+            && !last
+        {
+            out.push("\n");
+        }
+    }
+}
+
+/// Escapes a string so that it can be written between double quotes in JavaScript.
+pub(crate) fn escape_string(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+
+    for c in literal.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            // Line terminators must be escaped, other control characters are escaped to stay visible
+            '\u{2028}' | '\u{2029}' => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
         }
+    }
+
+    escaped
+}
+
+/// Formats a number the way JavaScript prints it, switching to exponent notation for very large
+/// and very small magnitudes.
+fn format_number(number: f64) -> String {
+    if number != 0.0 && !(1e-6..1e21).contains(&number.abs()) {
+        format!("{number:e}")
+    } else {
+        number.to_string()
+    }
+}
+
+/// Checks whether the expression is written starting with an object literal.
+fn starts_with_object(expression: &Expression) -> bool {
+    match expression {
+        Expression::ObjectExpression(..) => true,
+        Expression::Binary { left, .. } => starts_with_object(left),
+        Expression::FunctionCall { function, .. } => starts_with_object(function),
+        Expression::ArrayIndex { array, .. } => starts_with_object(array),
+        Expression::PropertyAccess { object, .. } => starts_with_object(object),
+        _ => false,
+    }
+}
 
-        code
+/// Whether `expression` is a negative number literal such as `-1`.
+fn is_negative_number(expression: &Expression) -> bool {
+    match expression {
+        Expression::Value(Value::Number(number), _) => *number < 0.0,
+        Expression::Unary {
+            op: UnaryOperator::Negate,
+            operand,
+            ..
+        } => matches!(**operand, Expression::Value(Value::Number(number), _) if number > 0.0),
+        _ => false,
     }
 }
@@ -0,0 +1,152 @@
+//! Checks where errors are reported, and that recovering from one does not hide the next.
+
+use klynt::diagnostic::Diagnostic;
+
+fn errors(source: &str) -> Vec<Diagnostic> {
+    match klynt::transpile(source.to_string()) {
+        Ok(code) => panic!("`{source}` transpiled to `{code}`"),
+        Err(diagnostics) => diagnostics,
+    }
+}
+
+/// Returns the line, column and message of every error in `source`.
+fn positions(source: &str) -> Vec<(usize, usize, String)> {
+    errors(source)
+        .into_iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.span.line,
+                diagnostic.span.column,
+                diagnostic.message,
+            )
+        })
+        .collect()
+}
+
+fn expected(errors: &[(usize, usize, &str)]) -> Vec<(usize, usize, String)> {
+    errors
+        .iter()
+        .map(|&(line, column, message)| (line, column, message.to_string()))
+        .collect()
+}
+
+#[test]
+fn missing_closing_braces_do_not_hide_later_errors() {
+    assert_eq!(
+        positions("let {a: 1;\nlet {b: 2};\nlet {c: @};\nset {d 3};"),
+        expected(&[
+            (1, 10, "expected `,` or `}`, found `;`"),
+            (3, 10, "expected `{` after `@`, found `}`"),
+            (4, 8, "expected `:` after the variable name, found `3`"),
+        ])
+    );
+    assert_eq!(
+        positions("let {a: ${f: 1, 2;\nlet {b: .{a, };\nlet {c: -};"),
+        expected(&[
+            (1, 18, "expected `,` or `}` after the argument, found `;`"),
+            (2, 14, "expected property name, found `}`"),
+            (3, 10, "expected expression, found `}`"),
+        ])
+    );
+}
+
+#[test]
+fn recovery_inside_a_function_keeps_its_closing_brace() {
+    assert_eq!(
+        positions("fn f {\n  let {a: +{1, 2};\n  let {b: *};\n}\nlet {c: /};"),
+        expected(&[
+            (2, 18, "expected `,` or `}`, found `;`"),
+            (3, 12, "expected `{` after `*`, found `}`"),
+            (5, 10, "expected `{` after `/`, found `}`"),
+        ])
+    );
+}
+
+#[test]
+fn blocks_of_a_failed_statement_are_skipped_whole() {
+    assert_eq!(
+        positions("when:{@} {\n  let {a: 1};\n}\nlet {c: /};"),
+        expected(&[
+            (1, 8, "expected `{` after `@`, found `}`"),
+            (4, 10, "expected `{` after `/`, found `}`"),
+        ])
+    );
+    assert_eq!(
+        positions("fn f:{a 1} {\n  let {a: 1};\n}\nlet {c: /};"),
+        expected(&[
+            (1, 9, "expected parameter name, found `1`"),
+            (4, 10, "expected `{` after `/`, found `}`"),
+        ])
+    );
+}
+
+#[test]
+fn unclosed_blocks_are_reported() {
+    assert_eq!(
+        positions("fn f {\n  let {a: 1};\n"),
+        expected(&[(1, 6, "unclosed `{`")])
+    );
+}
+
+#[test]
+fn missing_semicolons_are_reported_after_the_last_token() {
+    let message = "expected `;` after the returned expression, found `}`";
+
+    assert_eq!(positions("fn f { ret 1 }"), expected(&[(1, 13, message)]));
+    // Columns count chars, not bytes
+    assert_eq!(
+        positions("fn f { ret \"ééééé\" }"),
+        expected(&[(1, 19, message)])
+    );
+    assert_eq!(
+        positions("fn f { ret \"a\nbcd\" }"),
+        expected(&[(2, 5, message)])
+    );
+}
+
+#[test]
+fn missing_semicolons_are_underlined_where_they_go() {
+    let source = "fn f { ret \"a\nbcd\" }";
+    let rendered = errors(source)[0].render(source, false);
+
+    assert!(
+        rendered.contains("2 | bcd\" }\n  |     ^ add `;` here"),
+        "{rendered}"
+    );
+}
+
+#[test]
+fn prefixed_number_literals_out_of_range() {
+    let hex = format!("0x{}", "f".repeat(260));
+    let binary = format!("0b{}", "1".repeat(1100));
+    let hex_message = format!("number literal `{hex}` is out of range");
+    let binary_message = format!("number literal `{binary}` is out of range");
+
+    assert_eq!(
+        positions(&format!("let {{h: {hex}}};\nlet {{b: {binary}}};")),
+        expected(&[(1, 9, &hex_message), (2, 9, &binary_message)])
+    );
+    // The largest finite literals are still accepted
+    assert!(klynt::transpile(format!("let {{h: 0x{}}};", "f".repeat(255))).is_ok());
+}
+
+#[test]
+fn positions_after_nested_block_comments() {
+    assert_eq!(
+        positions("/* a /* b */ \n c */ let {x: @};"),
+        expected(&[(2, 16, "expected `{` after `@`, found `}`")])
+    );
+    assert_eq!(
+        positions("// a\n/* b\n/* c */ */\n  let {x: @};"),
+        expected(&[(4, 12, "expected `{` after `@`, found `}`")])
+    );
+}
+
+#[test]
+fn unterminated_block_comments() {
+    // Reported at the outermost `/*`, which the nested comment leaves open
+    assert_eq!(
+        positions("let {x: 1};\n  /* a /* b */"),
+        expected(&[(2, 3, "unterminated block comment")])
+    );
+}
@@ -0,0 +1,123 @@
+//! Checks the JavaScript emitted for `loop` and `each`, and where `break` and `continue` are
+//! allowed.
+
+fn transpile(source: &str) -> String {
+    klynt::transpile(source.to_string())
+        .unwrap_or_else(|diagnostics| panic!("`{source}` does not transpile: {diagnostics:?}"))
+}
+
+/// Returns the line, column and message of every error in `source`.
+fn errors(source: &str) -> Vec<(usize, usize, String)> {
+    klynt::transpile(source.to_string())
+        .expect_err("the source has errors")
+        .into_iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.span.line,
+                diagnostic.span.column,
+                diagnostic.message,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn while_loops() {
+    assert_eq!(transpile("loop:{x > 0} {}"), "while (x>0) {\n}\n");
+    assert_eq!(
+        transpile("loop:{>{x, 0}} { set {x: -{x, 1}}; when:{={x, 3}} { break; } continue; }"),
+        "while (x>0) {\nx=x-1;\nif (x==3) {\nbreak;\n}\ncontinue;\n}\n"
+    );
+}
+
+#[test]
+fn counted_loops() {
+    assert_eq!(
+        transpile("loop:{i, 0, 10} { ${log: i}; }"),
+        "for (let i=0;i<10;i++) {\nlog(i);\n}\n"
+    );
+    assert_eq!(
+        transpile("loop:{i, 0, 10, 2} { ${log: i}; }"),
+        "for (let i=0;i<10;i+=2) {\nlog(i);\n}\n"
+    );
+}
+
+#[test]
+fn each_loops() {
+    assert_eq!(
+        transpile("each:{item, [1, 2]} { when:{={item, 1}} { continue; } ${log: item}; }"),
+        "for (let item of [1,2]) {\nif (item==1) {\ncontinue;\n}\nlog(item);\n}\n"
+    );
+}
+
+#[test]
+fn break_and_continue_in_nested_loops() {
+    assert_eq!(
+        transpile("loop:{i, 0, 3} { each:{x, xs} { break; } continue; }"),
+        "for (let i=0;i<3;i++) {\nfor (let x of xs) {\nbreak;\n}\ncontinue;\n}\n"
+    );
+}
+
+#[test]
+fn break_and_continue_outside_of_a_loop() {
+    assert_eq!(
+        errors("break;\ncontinue;"),
+        vec![
+            (1, 1, String::from("`break` outside of a loop")),
+            (2, 1, String::from("`continue` outside of a loop")),
+        ]
+    );
+}
+
+#[test]
+fn functions_do_not_see_the_loops_around_them() {
+    assert_eq!(
+        errors("loop:{true} { fn f { break; } }"),
+        vec![(1, 22, String::from("`break` outside of a loop"))]
+    );
+    assert_eq!(
+        errors("loop:{true} { let {f: fn { continue; }}; }"),
+        vec![(1, 28, String::from("`continue` outside of a loop"))]
+    );
+    // Loops inside the function, and the loop after it, are still loops
+    assert_eq!(
+        transpile("loop:{true} { let {f: fn { loop:{true} { break; } }}; break; }"),
+        "while (true) {\nlet f=() => {\nwhile (true) {\nbreak;\n}\n};\nbreak;\n}\n"
+    );
+}
+
+#[test]
+fn each_item_can_be_reassigned() {
+    assert_eq!(
+        transpile("each:{x, xs} { set {x: 1}; }"),
+        "for (let x of xs) {\nx=1;\n}\n"
+    );
+}
+
+#[test]
+fn negative_steps_count_down() {
+    assert_eq!(
+        transpile("loop:{i, 10, 0, -1} { ${log: i}; }"),
+        "for (let i=10;i>0;i+=-1) {\nlog(i);\n}\n"
+    );
+    assert_eq!(
+        transpile("loop:{i, 10, 0, -{2}} {}"),
+        "for (let i=10;i>0;i+=-2) {\n}\n"
+    );
+}
+
+#[test]
+fn zero_steps_are_rejected() {
+    let diagnostics = klynt::transpile(String::from("loop:{i, 0, 10, 0} {}"))
+        .expect_err("a zero step never ends");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "the step of a counted loop cannot be zero"
+    );
+    assert_eq!(
+        (diagnostics[0].span.line, diagnostics[0].span.column),
+        (1, 17)
+    );
+}
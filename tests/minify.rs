@@ -0,0 +1,83 @@
+//! Checks the minified output: operands keep their meaning once the optional parentheses are
+//! dropped, and the short names given to locals follow the scopes of the generated code.
+
+use klynt::transpiler::OutputStyle;
+use klynt::transpiler::TranspileOptions;
+
+fn minify(source: &str) -> String {
+    let options = TranspileOptions::new().with_style(OutputStyle::Minified);
+
+    match klynt::transpile_with(source.to_string(), &options) {
+        Ok(output) => output.code,
+        Err(diagnostics) => panic!("`{source}` failed to transpile: {diagnostics:?}"),
+    }
+}
+
+#[test]
+fn loop_bound_keeps_its_parentheses() {
+    assert_eq!(
+        minify("fn f { loop:{i, 0, ||{a, b}} {} }"),
+        "function f(c){for(let d=0;d<(a||b);d++){}}"
+    );
+    assert_eq!(
+        minify("fn f { loop:{i, 0, ={a, b}} {} }"),
+        "function f(c){for(let d=0;d<(a==b);d++){}}"
+    );
+    assert_eq!(
+        minify("fn f { loop:{i, 0, >{a, b}} {} }"),
+        "function f(c){for(let d=0;d<(a>b);d++){}}"
+    );
+    assert_eq!(
+        minify("fn f { loop:{i, 0, a + b} {} }"),
+        "function f(c){for(let d=0;d<a+b;d++){}}"
+    );
+}
+
+#[test]
+fn sibling_blocks_reuse_short_names() {
+    assert_eq!(
+        minify(
+            "fn f:{first} { when:{first} { let {value: 1}; ret value; } \
+             or { let {other: 2}; ret other; } }"
+        ),
+        "function f(a){if(a){let b=1;return b;}else{let b=2;return b;}}"
+    );
+    assert_eq!(
+        minify("fn f:{n} { loop:{i, 0, n} {} loop:{j, 0, n} {} }"),
+        "function f(a){for(let b=0;b<a;b++){}for(let b=0;b<a;b++){}}"
+    );
+}
+
+#[test]
+fn closures_capture_outer_locals() {
+    assert_eq!(
+        minify(
+            "fn f:{count} { let {total: 0}; \
+             let {add: fn:{step} { set {total: +{total, step, count}}; }}; ret add; }"
+        ),
+        "function f(a){let b=0;let c=(d)=>{b=b+d+a;};return c;}"
+    );
+}
+
+#[test]
+fn closure_parameters_shadow_outer_locals() {
+    assert_eq!(
+        minify("fn f:{x} { let {g: fn:{x} { ret x; }}; ret +{x, $ {g: x}}; }"),
+        "function f(a){let b=(c)=>{return c;};return a+b(a);}"
+    );
+}
+
+#[test]
+fn top_level_names_are_kept() {
+    assert_eq!(
+        minify("let {total: 1}; fn f:{a} { ret +{a, total}; }"),
+        "let total=1;function f(b){return b+total;}"
+    );
+}
+
+#[test]
+fn statements_starting_with_an_object_are_parenthesized() {
+    // A leading `{` would be read as a block
+    assert_eq!(minify("${.{${(a: 1):}, x}:};"), "({a:1}().x());");
+    assert_eq!(minify("${@{${(a: 1):}, 0}:};"), "({a:1}()[0]());");
+}
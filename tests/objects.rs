@@ -0,0 +1,73 @@
+//! Checks object literals: key kinds, shorthand entries, source order and duplicate keys.
+
+use klynt::diagnostic::Diagnostic;
+
+/// Transpiles `let {o: <source>};` and returns the generated expression.
+fn transpile_object(source: &str) -> String {
+    let source = format!("let {{o: {source}}};");
+    let code = klynt::transpile(source.clone())
+        .unwrap_or_else(|diagnostics| panic!("`{source}` does not transpile: {diagnostics:?}"));
+
+    code.strip_prefix("let o=")
+        .and_then(|code| code.strip_suffix(";\n"))
+        .unwrap_or_else(|| panic!("unexpected output for `{source}`: {code}"))
+        .to_string()
+}
+
+fn errors(source: &str) -> Vec<Diagnostic> {
+    klynt::transpile(source.to_string()).expect_err("the source has errors")
+}
+
+#[test]
+fn entries_keep_source_order() {
+    assert_eq!(
+        transpile_object("(b: 1, a: 2, \"c d\": 3, [k]: 4)"),
+        "{b: 1,a: 2,\"c d\": 3,[k]: 4}"
+    );
+    assert_eq!(transpile_object("()"), "{}");
+}
+
+#[test]
+fn shorthand_entries() {
+    assert_eq!(transpile_object("(a, b)"), "{a: a,b: b}");
+    assert_eq!(transpile_object("(a, b: 2)"), "{a: a,b: 2}");
+}
+
+#[test]
+fn a_single_name_is_a_parenthesized_expression() {
+    assert_eq!(transpile_object("(a)"), "a");
+    assert_eq!(transpile_object("(a: a)"), "{a: a}");
+}
+
+#[test]
+fn duplicate_keys() {
+    let diagnostics = errors("let {o: (a: 1, b: 2,\n  a: 3)};");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "duplicate key `a` in object");
+    assert_eq!(
+        (diagnostics[0].span.line, diagnostics[0].span.column),
+        (2, 3)
+    );
+    assert_eq!(diagnostics[0].label.as_deref(), Some("redefined here"));
+    assert_eq!(
+        diagnostics[0].notes,
+        vec![String::from("`a` is first defined at line 1, column 10")]
+    );
+}
+
+#[test]
+fn identifier_and_string_keys_collide() {
+    let diagnostics = errors("let {o: (a: 1, \"a\": 2)};");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "duplicate key `a` in object");
+    assert_eq!(
+        (diagnostics[0].span.line, diagnostics[0].span.column),
+        (1, 16)
+    );
+    assert_eq!(
+        diagnostics[0].notes,
+        vec![String::from("`a` is first defined at line 1, column 10")]
+    );
+}
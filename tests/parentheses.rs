@@ -0,0 +1,424 @@
+//! Checks that the generated code only has the parentheses it needs, and that JavaScript reads
+//! every pairing of operators in the order of the Klynt source.
+//!
+//! The generated expressions are parsed back with the precedence rules of the ECMAScript
+//! grammar, written out here rather than taken from the crate, and compared to the tree the
+//! source was built from.
+
+use klynt::transpiler::OutputStyle;
+use klynt::transpiler::Target;
+use klynt::transpiler::TranspileOptions;
+
+/// Binary operators, in Klynt then in JavaScript.
+const BINARY: &[(&str, &str)] = &[
+    ("+", "+"),
+    ("-", "-"),
+    ("*", "*"),
+    ("/", "/"),
+    ("%", "%"),
+    ("**", "**"),
+    (">", ">"),
+    ("<", "<"),
+    (">=", ">="),
+    ("<=", "<="),
+    ("=", "=="),
+    ("!=", "!="),
+    ("&&", "&&"),
+    ("||", "||"),
+];
+
+const UNARY: &[&str] = &["-", "!"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tree {
+    Name(String),
+    Unary(String, Box<Tree>),
+    Binary(String, Box<Tree>, Box<Tree>),
+}
+
+/// A Klynt expression in prefix form along with the tree it stands for.
+struct Case {
+    source: String,
+    tree: Tree,
+}
+
+impl Case {
+    fn name(name: &str) -> Self {
+        Self {
+            source: name.to_string(),
+            tree: Tree::Name(name.to_string()),
+        }
+    }
+
+    fn unary(op: &str, operand: Case) -> Self {
+        Self {
+            source: format!("{op}{{{}}}", operand.source),
+            tree: Tree::Unary(op.to_string(), Box::new(operand.tree)),
+        }
+    }
+
+    fn binary(&(klynt, js): &(&str, &str), left: Case, right: Case) -> Self {
+        Self {
+            source: format!("{klynt}{{{}, {}}}", left.source, right.source),
+            tree: Tree::Binary(js.to_string(), Box::new(left.tree), Box::new(right.tree)),
+        }
+    }
+}
+
+fn styles() -> Vec<TranspileOptions> {
+    vec![
+        TranspileOptions::new(),
+        TranspileOptions::new().with_style(OutputStyle::Pretty),
+        TranspileOptions::new().with_style(OutputStyle::Minified),
+        TranspileOptions::new().with_strict_equality(true),
+    ]
+}
+
+/// Transpiles `let {result: <source>};` and returns the generated expression.
+fn transpile_expression(source: &str, options: &TranspileOptions) -> String {
+    let code = klynt::transpile_with(format!("let {{result: {source}}};"), options)
+        .unwrap_or_else(|diagnostics| panic!("`{source}` does not transpile: {diagnostics:?}"))
+        .code;
+
+    code.trim()
+        .strip_prefix("let result")
+        .and_then(|code| code.trim_start().strip_prefix('='))
+        .and_then(|code| code.strip_suffix(';'))
+        .unwrap_or_else(|| panic!("unexpected output for `{source}`: {code}"))
+        .trim()
+        .to_string()
+}
+
+/// Checks that `source` is read as `tree` and that removing any pair of its parentheses would
+/// change that.
+fn check(source: &str, tree: &Tree) {
+    for options in styles() {
+        check_generated(source, &transpile_expression(source, &options), tree);
+    }
+}
+
+/// Checks that the code `js` generated from `source` is read as `tree` and has no needless
+/// parentheses.
+fn check_generated(source: &str, js: &str, tree: &Tree) {
+    assert_eq!(
+        parse(js).as_ref(),
+        Ok(tree),
+        "`{source}` is transpiled to `{js}`"
+    );
+
+    for (open, close) in parentheses(js) {
+        let stripped = format!(
+            "{}{}{}",
+            &js[..open],
+            &js[open + 1..close],
+            &js[close + 1..]
+        );
+
+        assert_ne!(
+            parse(&stripped).as_ref(),
+            Ok(tree),
+            "`{source}` is transpiled to `{js}` with needless parentheses"
+        );
+    }
+}
+
+#[test]
+fn binary_pairings() {
+    for first in BINARY {
+        for second in BINARY {
+            for case in [
+                Case::binary(
+                    second,
+                    Case::binary(first, Case::name("a"), Case::name("b")),
+                    Case::name("c"),
+                ),
+                Case::binary(
+                    first,
+                    Case::name("a"),
+                    Case::binary(second, Case::name("b"), Case::name("c")),
+                ),
+            ] {
+                check(&case.source, &case.tree);
+            }
+        }
+    }
+}
+
+#[test]
+fn unary_and_binary_pairings() {
+    for op in UNARY {
+        for binary in BINARY {
+            for case in [
+                Case::unary(op, Case::binary(binary, Case::name("a"), Case::name("b"))),
+                Case::binary(binary, Case::unary(op, Case::name("a")), Case::name("b")),
+                Case::binary(binary, Case::name("a"), Case::unary(op, Case::name("b"))),
+            ] {
+                check(&case.source, &case.tree);
+            }
+        }
+
+        for inner in UNARY {
+            let case = Case::unary(op, Case::unary(inner, Case::name("a")));
+
+            check(&case.source, &case.tree);
+        }
+    }
+}
+
+#[test]
+fn variadic_operators_group_from_the_left() {
+    for operator in BINARY {
+        let (klynt, js) = *operator;
+        let tree = Tree::Binary(
+            js.to_string(),
+            Box::new(Tree::Binary(
+                js.to_string(),
+                Box::new(Tree::Name(String::from("a"))),
+                Box::new(Tree::Name(String::from("b"))),
+            )),
+            Box::new(Tree::Name(String::from("c"))),
+        );
+
+        check(&format!("{klynt}{{a, b, c}}"), &tree);
+    }
+}
+
+#[test]
+fn infix_expressions() {
+    let name = |name: &str| Box::new(Tree::Name(name.to_string()));
+    let binary = |op: &str, left, right| Box::new(Tree::Binary(op.to_string(), left, right));
+
+    check(
+        "(a + b) * 2 > 100 && ready",
+        &Tree::Binary(
+            String::from("&&"),
+            binary(
+                ">",
+                binary("*", binary("+", name("a"), name("b")), name("2")),
+                name("100"),
+            ),
+            name("ready"),
+        ),
+    );
+    // `-` without braces binds looser than `**`
+    check(
+        "-a ** b",
+        &Tree::Unary(String::from("-"), binary("**", name("a"), name("b"))),
+    );
+    check(
+        "a - (b - c)",
+        &Tree::Binary(
+            String::from("-"),
+            name("a"),
+            binary("-", name("b"), name("c")),
+        ),
+    );
+}
+
+#[test]
+fn loop_bounds() {
+    for operator in BINARY {
+        let case = Case::binary(operator, Case::name("a"), Case::name("b"));
+        let tree = Tree::Binary(
+            String::from("<"),
+            Box::new(Tree::Name(String::from("i"))),
+            Box::new(case.tree),
+        );
+        let source = format!("loop:{{i, 0, {}}} {{}}", case.source);
+
+        for options in styles() {
+            let code = klynt::transpile_with(source.clone(), &options)
+                .unwrap_or_else(|diagnostics| {
+                    panic!("`{source}` does not transpile: {diagnostics:?}")
+                })
+                .code;
+            let condition = code
+                .split(';')
+                .nth(1)
+                .unwrap_or_else(|| panic!("unexpected output for `{source}`: {code}"))
+                .trim();
+
+            check_generated(&source, condition, &tree);
+        }
+    }
+}
+
+#[test]
+fn operators_are_not_parenthesized() {
+    let options = TranspileOptions::new();
+
+    assert_eq!(transpile_expression(">{+{a, b}, 10}", &options), "a+b>10");
+    assert_eq!(transpile_expression("-{a, -{b}}", &options), "a- -b");
+    assert_eq!(transpile_expression("**{-{a}, b}", &options), "(-a)**b");
+    assert_eq!(
+        transpile_expression("${f: +{a, b}, fn:{x} { ret x; }}", &options),
+        "f(a+b,(x) => {\nreturn x;\n})"
+    );
+}
+
+#[test]
+fn math_pow_binds_like_a_call() {
+    let options = TranspileOptions::new().with_target(Target::Es2015);
+
+    assert_eq!(
+        transpile_expression("-{**{a, b}}", &options),
+        "-Math.pow(a,b)"
+    );
+    assert_eq!(
+        transpile_expression("*{**{+{a, b}, c}, d}", &options),
+        "Math.pow(a+b,c)*d"
+    );
+}
+
+/// Returns the byte offsets of the matching parentheses of `code`.
+fn parentheses(code: &str) -> Vec<(usize, usize)> {
+    let mut open = Vec::new();
+    let mut pairs = Vec::new();
+
+    for (index, c) in code.char_indices() {
+        match c {
+            '(' => open.push(index),
+            ')' => pairs.push((open.pop().expect("unbalanced parentheses"), index)),
+            _ => {}
+        }
+    }
+
+    pairs
+}
+
+/// Parses a JavaScript expression made of names, numbers, parentheses and the operators above.
+fn parse(code: &str) -> Result<Tree, String> {
+    let mut parser = JsParser {
+        tokens: tokenize(code)?,
+        position: 0,
+    };
+    let tree = parser.parse_binary(0)?;
+
+    match parser.tokens.get(parser.position) {
+        Some(token) => Err(format!("unexpected `{token}`")),
+        None => Ok(tree),
+    }
+}
+
+fn tokenize(code: &str) -> Result<Vec<String>, String> {
+    // Longest first, so that `--` is not read as two negations as JavaScript would not either
+    const PUNCTUATORS: &[&str] = &[
+        "===", "!==", "**", ">=", "<=", "==", "!=", "&&", "||", "--", "++", "+", "-", "*", "/",
+        "%", ">", "<", "!", "(", ")",
+    ];
+
+    let mut tokens = Vec::new();
+    let mut rest = code;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_alphanumeric() {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+
+            tokens.push(rest[..end].to_string());
+            rest = &rest[end..];
+        } else {
+            let punctuator = PUNCTUATORS
+                .iter()
+                .find(|punctuator| rest.starts_with(**punctuator))
+                .ok_or_else(|| format!("unexpected `{c}`"))?;
+
+            // Strict and loose equality only differ in what they compare, not in precedence
+            tokens.push(match *punctuator {
+                "===" => String::from("=="),
+                "!==" => String::from("!="),
+                punctuator => punctuator.to_string(),
+            });
+            rest = &rest[punctuator.len()..];
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Precedence of a binary operator and whether it is right-associative.
+fn js_precedence(op: &str) -> Option<(u8, bool)> {
+    match op {
+        "||" => Some((1, false)),
+        "&&" => Some((2, false)),
+        "==" | "!=" => Some((3, false)),
+        ">" | "<" | ">=" | "<=" => Some((4, false)),
+        "+" | "-" => Some((5, false)),
+        "*" | "/" | "%" => Some((6, false)),
+        "**" => Some((7, true)),
+        _ => None,
+    }
+}
+
+struct JsParser {
+    tokens: Vec<String>,
+    position: usize,
+}
+
+impl JsParser {
+    fn next(&mut self) -> Result<String, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| String::from("unexpected end of the expression"))?;
+
+        self.position += 1;
+
+        Ok(token)
+    }
+
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Tree, String> {
+        let (mut left, mut unary) = self.parse_unary()?;
+
+        while let Some((precedence, right_associative)) = self
+            .tokens
+            .get(self.position)
+            .and_then(|token| js_precedence(token))
+            .filter(|(precedence, _)| *precedence >= min_precedence)
+        {
+            let op = self.next()?;
+
+            if op == "**" && unary {
+                return Err(String::from("`**` after a unary expression"));
+            }
+
+            let right = self.parse_binary(if right_associative {
+                precedence
+            } else {
+                precedence + 1
+            })?;
+
+            left = Tree::Binary(op, Box::new(left), Box::new(right));
+            unary = false;
+        }
+
+        Ok(left)
+    }
+
+    /// Parses an operand, telling whether it is a unary expression without parentheses.
+    fn parse_unary(&mut self) -> Result<(Tree, bool), String> {
+        let token = self.next()?;
+
+        match token.as_str() {
+            "-" | "!" => {
+                let (operand, _) = self.parse_unary()?;
+
+                Ok((Tree::Unary(token, Box::new(operand)), true))
+            }
+            "(" => {
+                let tree = self.parse_binary(0)?;
+
+                match self.next()?.as_str() {
+                    ")" => Ok((tree, false)),
+                    token => Err(format!("expected `)`, found `{token}`")),
+                }
+            }
+            _ if token.chars().all(|c| c.is_ascii_alphanumeric()) => Ok((Tree::Name(token), false)),
+            _ => Err(format!("unexpected `{token}`")),
+        }
+    }
+}
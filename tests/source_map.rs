@@ -0,0 +1,35 @@
+//! Checks the positions recorded in generated source maps.
+
+use klynt::sourcemap::SourceMapOptions;
+use klynt::transpiler::OutputStyle;
+use klynt::transpiler::TranspileOptions;
+
+fn mappings(source: &str, style: OutputStyle) -> String {
+    let options = TranspileOptions::new()
+        .with_style(style)
+        .with_source_map(SourceMapOptions {
+            source_name: String::from("main.kl"),
+            url: Some(String::from("main.js.map")),
+            ..SourceMapOptions::default()
+        });
+
+    klynt::transpile_with(source.to_string(), &options)
+        .unwrap_or_else(|diagnostics| panic!("`{source}` does not transpile: {diagnostics:?}"))
+        .source_map
+        .expect("a source map was asked for")
+        .mappings
+}
+
+#[test]
+fn columns_are_counted_in_utf16_on_both_sides() {
+    // `let {t` is at column 15 of the source and `let t` at column 11 of the generated line,
+    // the emoji taking two UTF-16 code units in both
+    assert_eq!(
+        mappings("let {s: \"😀\"}; let {t: 1};", OutputStyle::Minified),
+        "AAAA,WAAe"
+    );
+    assert_eq!(
+        mappings("let {s: \"😀\"}; let {t: 1};", OutputStyle::Compact),
+        "AAAA;AAAe"
+    );
+}
@@ -0,0 +1,89 @@
+//! Checks that string escapes are decoded by the lexer and written back as valid JavaScript.
+
+/// Transpiles `let {s: <literal>};` and returns the generated string literal.
+fn transpile_string(literal: &str) -> String {
+    let source = format!("let {{s: {literal}}};");
+    let code = klynt::transpile(source.clone())
+        .unwrap_or_else(|diagnostics| panic!("`{source}` does not transpile: {diagnostics:?}"));
+
+    code.strip_prefix("let s=")
+        .and_then(|code| code.strip_suffix(";\n"))
+        .unwrap_or_else(|| panic!("unexpected output for `{source}`: {code}"))
+        .to_string()
+}
+
+/// Returns the line, column and message of every error in `source`.
+fn errors(source: &str) -> Vec<(usize, usize, String)> {
+    klynt::transpile(source.to_string())
+        .expect_err("the source has errors")
+        .into_iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.span.line,
+                diagnostic.span.column,
+                diagnostic.message,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn simple_escapes() {
+    assert_eq!(
+        transpile_string(r#""a\nb\tc\rd\\e\"f\'g""#),
+        r#""a\nb\tc\rd\\e\"f'g""#
+    );
+    // Control characters are written as `\u` escapes to stay visible
+    assert_eq!(transpile_string(r#""\0""#), r#""\u0000""#);
+}
+
+#[test]
+fn unicode_escapes() {
+    assert_eq!(transpile_string(r#""\u{41}\u{1F600}""#), "\"A😀\"");
+    assert_eq!(transpile_string(r#""\u{7f}""#), r#""\u007f""#);
+    assert_eq!(transpile_string("\"é😀\""), "\"é😀\"");
+}
+
+#[test]
+fn line_terminators_are_escaped() {
+    assert_eq!(
+        transpile_string(r#""\u{2028}\u{2029}""#),
+        r#""\u2028\u2029""#
+    );
+    assert_eq!(
+        transpile_string("\"\u{2028}\u{2029}\""),
+        r#""\u2028\u2029""#
+    );
+}
+
+#[test]
+fn invalid_escapes() {
+    let invalid = |escape: &str| format!("invalid escape sequence `{escape}`");
+
+    assert_eq!(
+        errors(r#"let {s: "\u{}"};"#),
+        vec![(1, 10, invalid(r"\u{}"))]
+    );
+    assert_eq!(errors(r#"let {s: "\u41"};"#), vec![(1, 10, invalid(r"\u"))]);
+    assert_eq!(
+        errors(r#"let {s: "\u{110000}"};"#),
+        vec![(1, 10, invalid(r"\u{110000}"))]
+    );
+    assert_eq!(errors(r#"let {s: "ok\q"};"#), vec![(1, 12, invalid(r"\q"))]);
+    // Only the first invalid escape of a string is reported, and lexing resumes after it
+    assert_eq!(
+        errors("let {s: \"é\\u{} \\x\"};\nlet {t: @};"),
+        vec![
+            (1, 11, invalid(r"\u{}")),
+            (2, 10, String::from("expected `{` after `@`, found `}`")),
+        ]
+    );
+}
+
+#[test]
+fn unterminated_strings() {
+    assert_eq!(
+        errors("let {a: 1};\nlet {s: \"abc};"),
+        vec![(2, 9, String::from("unterminated string literal"))]
+    );
+}